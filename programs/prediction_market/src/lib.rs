@@ -15,16 +15,56 @@ pub const POOL_SEED: &[u8] = b"pool";
 pub const POSITION_SEED: &[u8] = b"position";
 pub const VAULT_SEED: &[u8] = b"vault";
 pub const LP_POSITION_SEED: &[u8] = b"lp_position";
+pub const PARLAY_SEED: &[u8] = b"parlay";
+pub const LIMIT_ORDER_SEED: &[u8] = b"limit_order";
+pub const TRIGGER_SEED: &[u8] = b"trigger";
+pub const ORDER_BOOK_SEED: &[u8] = b"order_book";
+pub const BATCH_AUCTION_SEED: &[u8] = b"batch_auction";
+pub const RFQ_MAKER_SEED: &[u8] = b"rfq_maker";
+pub const CONFIG_SEED: &[u8] = b"config";
+pub const ORACLE_FEED_SEED: &[u8] = b"oracle_feed";
+pub const CREATOR_SEED: &[u8] = b"creator";
+pub const TREASURY_SEED: &[u8] = b"treasury";
+pub const METADATA_SEED: &[u8] = b"metadata";
+pub const PRICE_HISTORY_SEED: &[u8] = b"price_history";
+pub const CLOSED_MARKET_SEED: &[u8] = b"closed_market";
 
 pub const BASIS_POINTS: u64 = 10000;
-pub const LP_FEE_BPS: u64 = 30; // 0.3% fee
-pub const MIN_LIQUIDITY: u64 = 1000; // Minimum initial liquidity
+pub const DEFAULT_LP_FEE_BPS: u64 = 30; // 0.3% fee
+pub const MAX_PROTOCOL_FEE_BPS: u64 = 100; // 1% protocol fee ceiling
+pub const MAX_CREATOR_FEE_BPS: u64 = 200; // 2% creator fee ceiling
+pub const DEFAULT_MIN_LIQUIDITY: u64 = 1000; // Minimum initial liquidity
 pub const PRICE_DECIMALS: u64 = 1_000_000; // 6 decimal precision for prices
 pub const SHARE_DECIMALS: u64 = 1_000_000; // 6 decimal shares
-pub const MAX_TRADE_SIZE_BPS: u64 = 1000; // 10% of pool max per trade
-pub const RESOLUTION_DELAY: i64 = 300; // 5 minutes after expiration
+pub const DEFAULT_MAX_TRADE_SIZE_BPS: u64 = 1000; // 10% of pool max per trade
+pub const DEFAULT_RESOLUTION_DELAY: i64 = 300; // 5 minutes after expiration
 pub const MIN_SHARES_OUTPUT: u64 = 1000; // Minimum shares to prevent dust
 pub const MAX_ORACLE_STALENESS: i64 = 300; // 5 minutes max staleness
+pub const MAX_LP_FEE_BPS: u64 = 500; // 5% LP fee ceiling
+pub const MAX_RESOLUTION_DELAY: i64 = 86_400; // 1 day
+pub const MAX_ORACLE_STALENESS_LIMIT: u64 = 3600; // 1 hour ceiling on a market's staleness window
+pub const MANUAL_RESOLUTION_GRACE: i64 = 86_400; // 1 day without an oracle resolution
+pub const CLOSE_GRACE_PERIOD: i64 = 90 * 86_400; // 90 days after settlement
+pub const CRANK_FEE: u64 = 5_000; // Lamports per claim paid to the cranker
+pub const MAX_CRANK_FEE_BPS: u64 = 100; // Crank fee cap, 1% of the payout
+pub const MAX_CLAIM_MARKETS: usize = 20; // Keeps claim_many's per-market summaries within the 1 KiB return data limit
+pub const MAX_SYMBOL_LEN: usize = 16;
+pub const MAX_TITLE_LEN: usize = 128;
+pub const MAX_CATEGORY_LEN: usize = 32;
+pub const MAX_TAGS: usize = 8;
+pub const MAX_TAG_LEN: usize = 32;
+pub const MAX_RESOLUTION_SOURCE_LEN: usize = 256;
+pub const MAX_URI_LEN: usize = 200;
+pub const FEE_GROWTH_PRECISION: u128 = 1_000_000_000_000; // Fee growth per LP token scale
+pub const MIN_PARLAY_LEGS: usize = 2;
+pub const MAX_PARLAY_LEGS: usize = 8;
+pub const MAX_BOOK_ORDERS: usize = 32; // Resting orders per book side
+pub const MAX_BATCH_ORDERS: usize = 32; // Queued orders per batch
+pub const MIN_BATCH_INTERVAL: i64 = 1; // 1 second
+pub const MAX_BATCH_INTERVAL: i64 = 3600; // 1 hour
+pub const MAX_PRICE_CANDLES: usize = 128; // Candles kept per market before the oldest is overwritten
+pub const MIN_CANDLE_INTERVAL: i64 = 60; // 1 minute
+pub const MAX_CANDLE_INTERVAL: i64 = 86_400; // 1 day
 
 // ============================================================================
 // Program
@@ -41,7 +81,8 @@ pub mod prediction_market {
     /// * `market_id` - Unique identifier for the market
     /// * `strike_price` - The price threshold for resolution (scaled by 10^8 like Pyth)
     /// * `expiration` - Unix timestamp when the market expires
-    /// * `max_confidence` - Maximum acceptable confidence interval for resolution
+    /// * `max_confidence` - Maximum acceptable confidence interval for resolution,
+    ///   or 0 to use the registered feed's default
     /// * `description` - Short description of the market
     pub fn create_market(
         ctx: Context<CreateMarket>,
@@ -57,20 +98,59 @@ pub mod prediction_market {
         );
         require!(description.len() <= 128, MarketError::DescriptionTooLong);
 
+        // Only registered feeds may back new markets
+        let oracle_feed = &ctx.accounts.oracle_feed;
+        require!(oracle_feed.can_create, MarketError::OracleFeedNotPermitted);
+
         // Validate Pyth oracle account
         let price_feed = SolanaPriceAccount::account_info_to_feed(&ctx.accounts.pyth_price_account)
             .map_err(|_| MarketError::InvalidOraclePrice)?;
 
         // Verify oracle is publishing recent data
-        price_feed
+        let price = price_feed
             .get_price_no_older_than(
                 Clock::get()?.unix_timestamp,
                 3600, // 1 hour max staleness for creation
             )
             .ok_or(MarketError::InvalidOraclePrice)?;
+        require!(
+            price.expo == oracle_feed.exponent,
+            MarketError::OracleExponentMismatch
+        );
+
+        let max_confidence = if max_confidence == 0 {
+            oracle_feed.default_max_confidence
+        } else {
+            max_confidence
+        };
+
+        let config = &ctx.accounts.config;
+        if config.creator_allowlist_enabled {
+            require!(
+                ctx.accounts.creator_entry.is_some(),
+                MarketError::CreatorNotAllowed
+            );
+        }
+
+        // Creation fee is held in the treasury PDA until refunded or forfeited
+        let creation_fee = config.creation_fee;
+        if creation_fee > 0 {
+            let cpi_context = CpiContext::new(
+                ctx.accounts.system_program.to_account_info(),
+                anchor_lang::system_program::Transfer {
+                    from: ctx.accounts.authority.to_account_info(),
+                    to: ctx.accounts.treasury.to_account_info(),
+                },
+            );
+            anchor_lang::system_program::transfer(cpi_context, creation_fee)?;
+        }
 
         let market = &mut ctx.accounts.market;
         market.authority = ctx.accounts.authority.key();
+        market.pending_authority = None;
+        market.resolver = ctx.accounts.authority.key();
+        market.fee_collector = ctx.accounts.authority.key();
+        market.pauser = ctx.accounts.authority.key();
         market.market_id = market_id;
         market.strike_price = strike_price;
         market.expiration = expiration;
@@ -83,8 +163,32 @@ pub mod prediction_market {
         market.total_yes_shares = 0;
         market.total_no_shares = 0;
         market.description = description;
+        market.parent_market = None;
+        market.parent_outcome = None;
+        market.unsettled_children = 0;
+        market.batch_mode = false;
+        market.params = ctx.accounts.config.default_params;
+        market.protocol_fee_bps = ctx.accounts.config.protocol_fee_bps;
+        market.creator_fee_bps = 0;
+        market.total_volume = 0;
+        market.creation_fee_paid = creation_fee;
+        market.creation_fee_refund_volume = config.creation_fee_refund_volume;
+        market.metadata = None;
+        market.total_claimed = 0;
+        market.trading_started = false;
+        market.halted_by = None;
+        market.has_price_history = false;
         market.bump = ctx.bumps.market;
 
+        emit_cpi!(MarketCreated {
+            market: market.key(),
+            market_id,
+            authority: market.authority,
+            pyth_price_account: market.pyth_price_account,
+            strike_price,
+            expiration,
+        });
+
         msg!(
             "Market {} created with strike price {}",
             hex::encode(market_id),
@@ -93,10 +197,499 @@ pub mod prediction_market {
         Ok(())
     }
 
+    /// Initialize the global program config
+    ///
+    /// Only callable by the program's upgrade authority, who becomes the admin.
+    ///
+    /// # Arguments
+    /// * `treasury` - Account that receives protocol fees
+    /// * `protocol_fee_bps` - Protocol fee charged on trades, on top of the LP fee
+    /// * `max_creator_fee_bps` - Cap on the fee a market creator may charge
+    pub fn initialize_config(
+        ctx: Context<InitializeConfig>,
+        treasury: Pubkey,
+        protocol_fee_bps: u64,
+        max_creator_fee_bps: u64,
+    ) -> Result<()> {
+        require!(
+            protocol_fee_bps <= MAX_PROTOCOL_FEE_BPS && max_creator_fee_bps <= MAX_CREATOR_FEE_BPS,
+            MarketError::FeeTooHigh
+        );
+
+        let config = &mut ctx.accounts.config;
+        config.admin = ctx.accounts.admin.key();
+        config.treasury = treasury;
+        config.protocol_fee_bps = protocol_fee_bps;
+        config.max_creator_fee_bps = max_creator_fee_bps;
+        config.default_params = MarketParams::default();
+        config.pending_admin = None;
+        config.guardian = ctx.accounts.admin.key();
+        config.paused = false;
+        config.creator_allowlist_enabled = false;
+        config.creation_fee = 0;
+        config.creation_fee_refund_volume = 0;
+        config.bump = ctx.bumps.config;
+
+        msg!(
+            "Config initialized: protocol fee {} bps, creator fee cap {} bps",
+            protocol_fee_bps,
+            max_creator_fee_bps
+        );
+        Ok(())
+    }
+
+    /// Update protocol fee settings
+    ///
+    /// New markets snapshot the protocol fee at creation; existing markets keep theirs.
+    /// The creation policy must still hold at the new protocol fee.
+    pub fn update_fee_config(
+        ctx: Context<UpdateConfig>,
+        treasury: Pubkey,
+        protocol_fee_bps: u64,
+        max_creator_fee_bps: u64,
+    ) -> Result<()> {
+        require!(
+            protocol_fee_bps <= MAX_PROTOCOL_FEE_BPS && max_creator_fee_bps <= MAX_CREATOR_FEE_BPS,
+            MarketError::FeeTooHigh
+        );
+
+        let config = &mut ctx.accounts.config;
+        validate_creation_policy(
+            config.creation_fee,
+            config.creation_fee_refund_volume,
+            protocol_fee_bps,
+        )?;
+        config.treasury = treasury;
+        config.protocol_fee_bps = protocol_fee_bps;
+        config.max_creator_fee_bps = max_creator_fee_bps;
+
+        msg!(
+            "Fee config updated: protocol fee {} bps, creator fee cap {} bps",
+            protocol_fee_bps,
+            max_creator_fee_bps
+        );
+        Ok(())
+    }
+
+    /// Update the economic parameters new markets start with
+    ///
+    /// Existing markets keep the parameters they were created with.
+    pub fn update_default_params(ctx: Context<UpdateConfig>, params: MarketParams) -> Result<()> {
+        validate_market_params(&params)?;
+
+        ctx.accounts.config.default_params = params;
+        msg!("Default market params updated: {:?}", params);
+        Ok(())
+    }
+
+    /// Propose a new admin; takes effect once they call `accept_admin`
+    pub fn transfer_admin(ctx: Context<UpdateConfig>, new_admin: Pubkey) -> Result<()> {
+        ctx.accounts.config.pending_admin = Some(new_admin);
+        msg!("Admin transfer to {} proposed", new_admin);
+        Ok(())
+    }
+
+    /// Accept a pending admin transfer
+    pub fn accept_admin(ctx: Context<AcceptAdmin>) -> Result<()> {
+        let config = &mut ctx.accounts.config;
+        require!(
+            config.pending_admin == Some(ctx.accounts.new_admin.key()),
+            MarketError::Unauthorized
+        );
+
+        config.admin = ctx.accounts.new_admin.key();
+        config.pending_admin = None;
+        msg!("Admin transferred to {}", config.admin);
+        Ok(())
+    }
+
+    /// Configure who may create markets and what it costs
+    ///
+    /// # Arguments
+    /// * `allowlist_enabled` - Restrict market creation to allowlisted creators
+    /// * `creation_fee` - Lamports charged per market, held in the treasury PDA (0 disables the fee)
+    /// * `refund_volume` - AMM volume at which the creation fee becomes refundable (0 disables refunds)
+    pub fn set_creation_policy(
+        ctx: Context<UpdateConfig>,
+        allowlist_enabled: bool,
+        creation_fee: u64,
+        refund_volume: u64,
+    ) -> Result<()> {
+        let config = &mut ctx.accounts.config;
+        validate_creation_policy(creation_fee, refund_volume, config.protocol_fee_bps)?;
+        config.creator_allowlist_enabled = allowlist_enabled;
+        config.creation_fee = creation_fee;
+        config.creation_fee_refund_volume = refund_volume;
+
+        msg!(
+            "Creation policy: allowlist {}, fee {}, refund volume {}",
+            allowlist_enabled,
+            creation_fee,
+            refund_volume
+        );
+        Ok(())
+    }
+
+    /// Allow a key to create markets while the allowlist is enabled
+    pub fn add_allowed_creator(ctx: Context<AddAllowedCreator>, creator: Pubkey) -> Result<()> {
+        let entry = &mut ctx.accounts.creator_entry;
+        entry.creator = creator;
+        entry.bump = ctx.bumps.creator_entry;
+
+        msg!("Creator {} allowlisted", creator);
+        Ok(())
+    }
+
+    /// Remove a key from the creator allowlist
+    pub fn remove_allowed_creator(ctx: Context<RemoveAllowedCreator>) -> Result<()> {
+        msg!(
+            "Creator {} removed from allowlist",
+            ctx.accounts.creator_entry.creator
+        );
+        Ok(())
+    }
+
+    /// Set the guardian key allowed to pause the protocol and halt markets
+    pub fn set_guardian(ctx: Context<UpdateConfig>, guardian: Pubkey) -> Result<()> {
+        ctx.accounts.config.guardian = guardian;
+        msg!("Guardian set to {}", guardian);
+        Ok(())
+    }
+
+    /// Pause or unpause trading, liquidity deposits and delegation across all markets
+    ///
+    /// Claims and refunds keep working while paused.
+    pub fn set_paused(ctx: Context<GuardianAction>, paused: bool) -> Result<()> {
+        ctx.accounts.config.paused = paused;
+        msg!("Protocol paused: {}", paused);
+        Ok(())
+    }
+
+    /// Halt trading on a single market
+    ///
+    /// Callable by the protocol guardian or the market's pauser. The halting
+    /// role is recorded so a guardian halt cannot be lifted by the pauser.
+    pub fn halt_market(ctx: Context<HaltMarket>) -> Result<()> {
+        let role = ctx.accounts.check_pauser()?;
+
+        let market = &mut ctx.accounts.market;
+        require!(
+            market.status == MarketStatus::Active,
+            MarketError::MarketNotActive
+        );
+
+        market.status = MarketStatus::Halted;
+        market.halted_by = Some(role);
+
+        emit_cpi!(MarketHalted {
+            market: market.key(),
+            signer: ctx.accounts.signer.key(),
+            role,
+        });
+
+        msg!(
+            "Market {} halted by {:?}",
+            hex::encode(market.market_id),
+            role
+        );
+        Ok(())
+    }
+
+    /// Resume trading on a halted market
+    ///
+    /// Only the guardian may lift a halt the guardian placed.
+    pub fn resume_market(ctx: Context<HaltMarket>) -> Result<()> {
+        let role = ctx.accounts.check_pauser()?;
+
+        let market = &mut ctx.accounts.market;
+        require!(
+            market.status == MarketStatus::Halted,
+            MarketError::MarketNotHalted
+        );
+        if market.halted_by == Some(HaltRole::Guardian) {
+            require!(role == HaltRole::Guardian, MarketError::Unauthorized);
+        }
+
+        market.status = MarketStatus::Active;
+        market.halted_by = None;
+
+        emit_cpi!(MarketResumed {
+            market: market.key(),
+            signer: ctx.accounts.signer.key(),
+            role,
+        });
+
+        msg!("Market {} resumed", hex::encode(market.market_id));
+        Ok(())
+    }
+
+    /// Register a Pyth feed that markets may be created against
+    ///
+    /// # Arguments
+    /// * `symbol` - Human-readable symbol, e.g. "SOL/USD"
+    /// * `exponent` - Expected price exponent, checked against the feed
+    /// * `default_max_confidence` - Confidence limit used when a market does not set one
+    /// * `can_create` - Whether new markets may use this feed
+    /// * `can_resolve` - Whether markets may resolve from this feed
+    pub fn add_oracle_feed(
+        ctx: Context<AddOracleFeed>,
+        symbol: String,
+        exponent: i32,
+        default_max_confidence: u64,
+        can_create: bool,
+        can_resolve: bool,
+    ) -> Result<()> {
+        require!(symbol.len() <= MAX_SYMBOL_LEN, MarketError::SymbolTooLong);
+
+        let price_feed = SolanaPriceAccount::account_info_to_feed(&ctx.accounts.pyth_price_account)
+            .map_err(|_| MarketError::InvalidOraclePrice)?;
+        require!(
+            price_feed.get_price_unchecked().expo == exponent,
+            MarketError::OracleExponentMismatch
+        );
+
+        let oracle_feed = &mut ctx.accounts.oracle_feed;
+        oracle_feed.feed = ctx.accounts.pyth_price_account.key();
+        oracle_feed.symbol = symbol;
+        oracle_feed.exponent = exponent;
+        oracle_feed.default_max_confidence = default_max_confidence;
+        oracle_feed.can_create = can_create;
+        oracle_feed.can_resolve = can_resolve;
+        oracle_feed.bump = ctx.bumps.oracle_feed;
+
+        msg!(
+            "Oracle feed {} registered as {}",
+            oracle_feed.feed,
+            oracle_feed.symbol
+        );
+        Ok(())
+    }
+
+    /// Update a registered feed's defaults and permissions
+    pub fn update_oracle_feed(
+        ctx: Context<UpdateOracleFeed>,
+        default_max_confidence: u64,
+        can_create: bool,
+        can_resolve: bool,
+    ) -> Result<()> {
+        let oracle_feed = &mut ctx.accounts.oracle_feed;
+        oracle_feed.default_max_confidence = default_max_confidence;
+        oracle_feed.can_create = can_create;
+        oracle_feed.can_resolve = can_resolve;
+
+        msg!(
+            "Oracle feed {} updated: create {}, resolve {}",
+            oracle_feed.symbol,
+            can_create,
+            can_resolve
+        );
+        Ok(())
+    }
+
+    /// Remove a feed from the registry, returning its rent to the admin
+    ///
+    /// Markets already using the feed can then only be resolved manually.
+    pub fn remove_oracle_feed(_ctx: Context<RemoveOracleFeed>) -> Result<()> {
+        msg!("Oracle feed removed");
+        Ok(())
+    }
+
+    /// Override the config defaults for this market
+    ///
+    /// Values must stay within the program-wide bounds and can only be changed
+    /// before trading starts.
+    pub fn set_market_params(ctx: Context<SetMarketParams>, params: MarketParams) -> Result<()> {
+        let market = &mut ctx.accounts.market;
+        require!(
+            market.status == MarketStatus::Active,
+            MarketError::MarketNotActive
+        );
+        require!(
+            market.authority == ctx.accounts.authority.key(),
+            MarketError::Unauthorized
+        );
+        require!(!market.trading_started, MarketError::TradingAlreadyStarted);
+        validate_market_params(&params)?;
+
+        market.params = params;
+        msg!("Market params set: {:?}", params);
+        Ok(())
+    }
+
+    /// Set the creator fee charged on this market's trades
+    ///
+    /// Capped by the global config and only settable before trading starts.
+    pub fn set_creator_fee(ctx: Context<SetCreatorFee>, creator_fee_bps: u64) -> Result<()> {
+        let market = &mut ctx.accounts.market;
+        require!(
+            market.status == MarketStatus::Active,
+            MarketError::MarketNotActive
+        );
+        require!(
+            market.authority == ctx.accounts.authority.key(),
+            MarketError::Unauthorized
+        );
+        require!(!market.trading_started, MarketError::TradingAlreadyStarted);
+        require!(
+            creator_fee_bps <= ctx.accounts.config.max_creator_fee_bps,
+            MarketError::FeeTooHigh
+        );
+
+        market.creator_fee_bps = creator_fee_bps;
+        msg!("Creator fee set to {} bps", creator_fee_bps);
+        Ok(())
+    }
+
+    /// Propose a new market authority; takes effect once they call `accept_market_authority`
+    pub fn transfer_market_authority(
+        ctx: Context<MarketAuthorityAction>,
+        new_authority: Pubkey,
+    ) -> Result<()> {
+        let market = &mut ctx.accounts.market;
+        require!(
+            market.authority == ctx.accounts.authority.key(),
+            MarketError::Unauthorized
+        );
+
+        market.pending_authority = Some(new_authority);
+        msg!("Market authority transfer to {} proposed", new_authority);
+        Ok(())
+    }
+
+    /// Accept a pending market authority transfer
+    pub fn accept_market_authority(ctx: Context<MarketAuthorityAction>) -> Result<()> {
+        let market = &mut ctx.accounts.market;
+        require!(
+            market.pending_authority == Some(ctx.accounts.authority.key()),
+            MarketError::Unauthorized
+        );
+
+        market.authority = ctx.accounts.authority.key();
+        market.pending_authority = None;
+        msg!("Market authority transferred to {}", market.authority);
+        Ok(())
+    }
+
+    /// Assign the market's role keys
+    ///
+    /// # Arguments
+    /// * `resolver` - May resolve manually if the oracle fails to resolve the market
+    /// * `fee_collector` - Receives creator fees
+    /// * `pauser` - May halt and resume trading on this market
+    pub fn set_market_roles(
+        ctx: Context<MarketAuthorityAction>,
+        resolver: Pubkey,
+        fee_collector: Pubkey,
+        pauser: Pubkey,
+    ) -> Result<()> {
+        let market = &mut ctx.accounts.market;
+        require!(
+            market.authority == ctx.accounts.authority.key(),
+            MarketError::Unauthorized
+        );
+
+        market.resolver = resolver;
+        market.fee_collector = fee_collector;
+        market.pauser = pauser;
+        msg!(
+            "Market roles set: resolver {}, fee collector {}, pauser {}",
+            resolver,
+            fee_collector,
+            pauser
+        );
+        Ok(())
+    }
+
+    /// Attach a metadata account to a market
+    ///
+    /// Metadata is editable by the authority until trading starts, then frozen.
+    pub fn create_market_metadata(
+        ctx: Context<CreateMarketMetadata>,
+        args: MarketMetadataArgs,
+    ) -> Result<()> {
+        check_metadata_editable(&ctx.accounts.market, &ctx.accounts.authority)?;
+        validate_metadata(&args)?;
+
+        let metadata = &mut ctx.accounts.metadata;
+        metadata.market = ctx.accounts.market.key();
+        metadata.bump = ctx.bumps.metadata;
+        metadata.apply(args);
+
+        let metadata_key = metadata.key();
+        ctx.accounts.market.metadata = Some(metadata_key);
+
+        msg!("Metadata {} attached to market", metadata_key);
+        Ok(())
+    }
+
+    /// Replace a market's metadata, resizing the account to fit
+    pub fn update_market_metadata(
+        ctx: Context<UpdateMarketMetadata>,
+        args: MarketMetadataArgs,
+    ) -> Result<()> {
+        check_metadata_editable(&ctx.accounts.market, &ctx.accounts.authority)?;
+        validate_metadata(&args)?;
+
+        ctx.accounts.metadata.apply(args);
+
+        msg!("Market metadata updated");
+        Ok(())
+    }
+
+    /// Make this market conditional on the outcome of another market
+    ///
+    /// If the parent resolves to anything other than `parent_outcome` (or is
+    /// cancelled/invalidated), this market becomes `Invalid` and refundable.
+    /// Can only be set before any shares have been traded.
+    ///
+    /// # Arguments
+    /// * `parent_outcome` - The parent outcome this market is conditional on
+    pub fn set_parent_market(ctx: Context<SetParentMarket>, parent_outcome: Outcome) -> Result<()> {
+        let market = &ctx.accounts.market;
+        let parent = &ctx.accounts.parent_market;
+
+        require!(
+            market.status == MarketStatus::Active,
+            MarketError::MarketNotActive
+        );
+        require!(
+            market.authority == ctx.accounts.authority.key(),
+            MarketError::Unauthorized
+        );
+        require!(!market.trading_started, MarketError::TradingAlreadyStarted);
+        require!(
+            market.parent_market.is_none(),
+            MarketError::InvalidParentMarket
+        );
+        require_keys_neq!(parent.key(), market.key(), MarketError::InvalidParentMarket);
+        require!(
+            parent.status == MarketStatus::Active,
+            MarketError::InvalidParentMarket
+        );
+        require!(
+            parent.expiration <= market.expiration,
+            MarketError::InvalidParentMarket
+        );
+
+        let parent_key = parent.key();
+        ctx.accounts.parent_market.unsettled_children += 1;
+        let market = &mut ctx.accounts.market;
+        market.parent_market = Some(parent_key);
+        market.parent_outcome = Some(parent_outcome);
+
+        msg!(
+            "Market {} is now conditional on {:?} in parent {}",
+            hex::encode(market.market_id),
+            parent_outcome,
+            parent_key
+        );
+        Ok(())
+    }
+
     /// Initialize the liquidity pool for a market
     pub fn initialize_pool(ctx: Context<InitializePool>, initial_liquidity: u64) -> Result<()> {
         require!(
-            initial_liquidity >= MIN_LIQUIDITY,
+            initial_liquidity >= ctx.accounts.market.params.min_liquidity,
             MarketError::InsufficientLiquidity
         );
 
@@ -117,7 +710,11 @@ pub mod prediction_market {
         pool.no_reserve = initial_liquidity;
         pool.total_liquidity = initial_liquidity * 2;
         pool.total_fees_collected = 0;
+        pool.fee_growth_per_lp = 0;
+        pool.protocol_fees_accrued = 0;
+        pool.creator_fees_accrued = 0;
         pool.lp_token_supply = initial_liquidity * 2; // Initial LP tokens = liquidity amount
+        pool.lp_position_count = 1;
         pool.bump = ctx.bumps.pool;
 
         // Create LP position for initial provider
@@ -125,8 +722,18 @@ pub mod prediction_market {
         lp_position.user = ctx.accounts.authority.key();
         lp_position.pool = ctx.accounts.pool.key();
         lp_position.lp_tokens = initial_liquidity * 2;
+        lp_position.fee_growth_checkpoint = 0;
+        lp_position.fees_owed = 0;
         lp_position.bump = ctx.bumps.lp_position;
 
+        emit_cpi!(PoolInitialized {
+            market: ctx.accounts.market.key(),
+            pool: ctx.accounts.pool.key(),
+            provider: ctx.accounts.authority.key(),
+            liquidity: initial_liquidity * 2,
+            lp_tokens: initial_liquidity * 2,
+        });
+
         msg!(
             "Pool initialized with {} lamports liquidity, {} LP tokens minted",
             initial_liquidity * 2,
@@ -141,24 +748,11 @@ pub mod prediction_market {
         amount: u64,
         min_lp_tokens: u64,
     ) -> Result<()> {
-        require!(
-            ctx.accounts.market.status == MarketStatus::Active,
-            MarketError::MarketNotActive
-        );
+        check_liquidity_open(&ctx.accounts.market, &ctx.accounts.config)?;
         require!(amount > 0, MarketError::InvalidAmount);
 
         let pool = &mut ctx.accounts.pool;
-        let total_liquidity = pool.total_liquidity;
-        let total_lp_shares = pool.lp_token_supply;
-
-        // Calculate LP tokens to mint
-        // lp_tokens = amount * total_lp_shares / total_liquidity
-        // Since we enforce 50/50 added value, we can just use total liquidity
-        let lp_tokens_to_mint = if total_liquidity == 0 {
-            amount
-        } else {
-            (amount as u128 * total_lp_shares as u128 / total_liquidity as u128) as u64
-        };
+        let lp_tokens_to_mint = calculate_lp_tokens_to_mint(pool, amount);
 
         require!(
             lp_tokens_to_mint >= min_lp_tokens,
@@ -182,15 +776,26 @@ pub mod prediction_market {
         pool.total_liquidity += amount;
         pool.lp_token_supply += lp_tokens_to_mint;
 
-        // Update user position
+        // Update user position, banking fees earned on the old balance first
         let lp_position = &mut ctx.accounts.lp_position;
+        settle_lp_fees(pool, lp_position);
         lp_position.lp_tokens += lp_tokens_to_mint;
         if lp_position.user == Pubkey::default() {
             lp_position.user = ctx.accounts.user.key();
             lp_position.pool = pool.key();
             lp_position.bump = ctx.bumps.lp_position;
+            pool.lp_position_count += 1;
         }
 
+        emit_cpi!(LiquidityAdded {
+            market: ctx.accounts.market.key(),
+            user: ctx.accounts.user.key(),
+            amount,
+            lp_tokens: lp_tokens_to_mint,
+            yes_reserve: pool.yes_reserve,
+            no_reserve: pool.no_reserve,
+        });
+
         msg!(
             "Added {} lamports liquidity, minted {} LP tokens",
             amount,
@@ -214,15 +819,7 @@ pub mod prediction_market {
         );
 
         let pool = &mut ctx.accounts.pool;
-        let total_liquidity = pool.total_liquidity;
-        let total_lp_shares = pool.lp_token_supply;
-
-        require!(total_lp_shares > 0, MarketError::InsufficientLiquidity);
-
-        // Calculate amount to return
-        // amount = lp_tokens * total_liquidity / total_lp_shares
-        let amount_out =
-            (lp_tokens as u128 * total_liquidity as u128 / total_lp_shares as u128) as u64;
+        let amount_out = calculate_liquidity_out(pool, lp_tokens)?;
 
         require!(amount_out >= min_amount_out, MarketError::SlippageExceeded);
 
@@ -234,21 +831,14 @@ pub mod prediction_market {
         );
 
         // Transfer SOL from vault to user
-        let bump = ctx.bumps.vault;
-        let bump_slice = &[bump];
-        let market_key = ctx.accounts.market.key();
-        let seeds = &[VAULT_SEED, market_key.as_ref(), bump_slice];
-        let signer_seeds = &[&seeds[..]];
-
-        let cpi_context = CpiContext::new_with_signer(
-            ctx.accounts.system_program.to_account_info(),
-            anchor_lang::system_program::Transfer {
-                from: ctx.accounts.vault.to_account_info(),
-                to: ctx.accounts.user.to_account_info(),
-            },
-            signer_seeds,
-        );
-        anchor_lang::system_program::transfer(cpi_context, amount_out)?;
+        transfer_from_vault(
+            &ctx.accounts.vault,
+            &ctx.accounts.user.to_account_info(),
+            &ctx.accounts.system_program.to_account_info(),
+            &ctx.accounts.market.key(),
+            ctx.bumps.vault,
+            amount_out,
+        )?;
 
         // Update pool state
         let half_amount = amount_out / 2;
@@ -257,10 +847,20 @@ pub mod prediction_market {
         pool.total_liquidity = pool.total_liquidity.saturating_sub(amount_out);
         pool.lp_token_supply = pool.lp_token_supply.saturating_sub(lp_tokens);
 
-        // Update user position
+        // Update user position, banking fees earned on the old balance first
+        settle_lp_fees(pool, lp_position);
         lp_position.lp_tokens -= lp_tokens;
 
-        msg!(
+        emit_cpi!(LiquidityRemoved {
+            market: ctx.accounts.market.key(),
+            user: ctx.accounts.user.key(),
+            amount: amount_out,
+            lp_tokens,
+            yes_reserve: pool.yes_reserve,
+            no_reserve: pool.no_reserve,
+        });
+
+        msg!(
             "Removed liquidity: burned {} LP tokens for {} lamports",
             lp_tokens,
             amount_out
@@ -268,6 +868,179 @@ pub mod prediction_market {
         Ok(())
     }
 
+    /// Claim trading fees earned by an LP position
+    pub fn claim_lp_fees(ctx: Context<ClaimLpFees>) -> Result<()> {
+        let lp_position = &mut ctx.accounts.lp_position;
+        settle_lp_fees(&ctx.accounts.pool, lp_position);
+
+        let fees = lp_position.fees_owed;
+        require!(fees > 0, MarketError::NoFeesToClaim);
+
+        // Check vault balance
+        let vault_lamports = ctx.accounts.vault.lamports();
+        require!(vault_lamports >= fees, MarketError::InsufficientVaultFunds);
+
+        // Transfer fees from vault
+        transfer_from_vault(
+            &ctx.accounts.vault,
+            &ctx.accounts.user.to_account_info(),
+            &ctx.accounts.system_program.to_account_info(),
+            &ctx.accounts.market.key(),
+            ctx.bumps.vault,
+            fees,
+        )?;
+
+        let lp_position = &mut ctx.accounts.lp_position;
+        lp_position.fees_owed = 0;
+
+        emit_cpi!(LpFeesClaimed {
+            market: ctx.accounts.market.key(),
+            user: ctx.accounts.user.key(),
+            amount: fees,
+        });
+
+        msg!("Claimed {} lamports of LP fees", fees);
+        Ok(())
+    }
+
+    /// Sweep accrued protocol fees from a market's vault to the treasury
+    pub fn collect_protocol_fees(ctx: Context<CollectProtocolFees>) -> Result<()> {
+        let fees = ctx.accounts.pool.protocol_fees_accrued;
+        require!(fees > 0, MarketError::NoFeesToClaim);
+        require!(
+            ctx.accounts.vault.lamports() >= fees,
+            MarketError::InsufficientVaultFunds
+        );
+
+        transfer_from_vault(
+            &ctx.accounts.vault,
+            &ctx.accounts.treasury,
+            &ctx.accounts.system_program.to_account_info(),
+            &ctx.accounts.market.key(),
+            ctx.bumps.vault,
+            fees,
+        )?;
+
+        ctx.accounts.pool.protocol_fees_accrued = 0;
+
+        emit_cpi!(ProtocolFeesCollected {
+            market: ctx.accounts.market.key(),
+            treasury: ctx.accounts.treasury.key(),
+            amount: fees,
+        });
+
+        msg!("Collected {} lamports of protocol fees", fees);
+        Ok(())
+    }
+
+    /// Withdraw accrued creator fees to the market's fee collector
+    pub fn collect_creator_fees(ctx: Context<CollectCreatorFees>) -> Result<()> {
+        require!(
+            ctx.accounts.market.fee_collector == ctx.accounts.fee_collector.key(),
+            MarketError::Unauthorized
+        );
+
+        let fees = ctx.accounts.pool.creator_fees_accrued;
+        require!(fees > 0, MarketError::NoFeesToClaim);
+        require!(
+            ctx.accounts.vault.lamports() >= fees,
+            MarketError::InsufficientVaultFunds
+        );
+
+        transfer_from_vault(
+            &ctx.accounts.vault,
+            &ctx.accounts.fee_collector.to_account_info(),
+            &ctx.accounts.system_program.to_account_info(),
+            &ctx.accounts.market.key(),
+            ctx.bumps.vault,
+            fees,
+        )?;
+
+        ctx.accounts.pool.creator_fees_accrued = 0;
+
+        emit_cpi!(CreatorFeesCollected {
+            market: ctx.accounts.market.key(),
+            fee_collector: ctx.accounts.fee_collector.key(),
+            amount: fees,
+        });
+
+        msg!("Collected {} lamports of creator fees", fees);
+        Ok(())
+    }
+
+    /// Refund a market's creation fee once its AMM volume reaches the threshold set at creation
+    pub fn refund_creation_fee(ctx: Context<RefundCreationFee>) -> Result<()> {
+        let market = &ctx.accounts.market;
+        require!(
+            market.authority == ctx.accounts.authority.key(),
+            MarketError::Unauthorized
+        );
+
+        let fee = market.creation_fee_paid;
+        require!(fee > 0, MarketError::NoRefund);
+
+        let refund_volume = market.creation_fee_refund_volume;
+        require!(
+            refund_volume > 0 && market.total_volume >= refund_volume,
+            MarketError::VolumeThresholdNotMet
+        );
+
+        let bump_slice = &[ctx.bumps.treasury];
+        let seeds = &[TREASURY_SEED, bump_slice];
+        let signer_seeds = &[&seeds[..]];
+        let cpi_context = CpiContext::new_with_signer(
+            ctx.accounts.system_program.to_account_info(),
+            anchor_lang::system_program::Transfer {
+                from: ctx.accounts.treasury.to_account_info(),
+                to: ctx.accounts.authority.to_account_info(),
+            },
+            signer_seeds,
+        );
+        anchor_lang::system_program::transfer(cpi_context, fee)?;
+
+        ctx.accounts.market.creation_fee_paid = 0;
+        msg!("Refunded {} lamport creation fee", fee);
+        Ok(())
+    }
+
+    /// Move the creation fee of a settled market that missed its refund threshold
+    /// from the treasury PDA to the configured treasury. Permissionless.
+    pub fn forfeit_creation_fee(ctx: Context<ForfeitCreationFee>) -> Result<()> {
+        let market = &ctx.accounts.market;
+        require!(
+            matches!(
+                market.status,
+                MarketStatus::Resolved | MarketStatus::Cancelled | MarketStatus::Invalid
+            ),
+            MarketError::MarketNotSettled
+        );
+
+        let fee = market.creation_fee_paid;
+        require!(fee > 0, MarketError::NoRefund);
+        require!(
+            market.creation_fee_refund_volume == 0
+                || market.total_volume < market.creation_fee_refund_volume,
+            MarketError::RefundAvailable
+        );
+
+        let bump_slice = &[ctx.bumps.fee_escrow];
+        let seeds = &[TREASURY_SEED, bump_slice];
+        let signer_seeds = &[&seeds[..]];
+        let cpi_context = CpiContext::new_with_signer(
+            ctx.accounts.system_program.to_account_info(),
+            anchor_lang::system_program::Transfer {
+                from: ctx.accounts.fee_escrow.to_account_info(),
+                to: ctx.accounts.treasury.to_account_info(),
+            },
+            signer_seeds,
+        );
+        anchor_lang::system_program::transfer(cpi_context, fee)?;
+
+        ctx.accounts.market.creation_fee_paid = 0;
+        msg!("Forfeited {} lamport creation fee to the treasury", fee);
+        Ok(())
+    }
+
     /// Buy YES or NO shares using the AMM
     /// This instruction is designed to run on ephemeral rollups for instant execution
     pub fn buy_shares(
@@ -276,109 +1049,78 @@ pub mod prediction_market {
         amount_in: u64,
         min_shares_out: u64,
     ) -> Result<()> {
-        require!(
-            ctx.accounts.market.status == MarketStatus::Active,
-            MarketError::MarketNotActive
-        );
+        check_amm_trade(&ctx.accounts.market, &ctx.accounts.config)?;
         require!(amount_in > 0, MarketError::InvalidAmount);
 
-        let pool = &mut ctx.accounts.pool;
+        let swap = calculate_buy(&ctx.accounts.pool, &ctx.accounts.market, side, amount_in)?;
 
-        // Check for max trade size (10% of total liquidity)
         require!(
-            amount_in <= pool.total_liquidity * MAX_TRADE_SIZE_BPS / BASIS_POINTS,
-            MarketError::TradeExceedsMaxSize
+            swap.shares_out >= min_shares_out,
+            MarketError::SlippageExceeded
         );
         require!(
-            pool.yes_reserve > 0 && pool.no_reserve > 0,
-            MarketError::PoolNotInitialized
+            swap.shares_out >= MIN_SHARES_OUTPUT,
+            MarketError::OutputTooSmall
         );
 
-        // Calculate fee
-        let fee = amount_in * LP_FEE_BPS / BASIS_POINTS;
-        let amount_after_fee = amount_in - fee;
-
-        // Calculate shares using constant product formula
-        // For buying YES: shares_out = yes_reserve - (k / (no_reserve + amount))
-        let (reserve_in, reserve_out) = match side {
-            Outcome::Yes => (pool.no_reserve, pool.yes_reserve),
-            Outcome::No => (pool.yes_reserve, pool.no_reserve),
-        };
-
-        let k = reserve_in as u128 * reserve_out as u128;
-        let new_reserve_in = reserve_in + amount_after_fee;
-        let new_reserve_out = (k / new_reserve_in as u128) as u64;
-        let shares_out = reserve_out.saturating_sub(new_reserve_out);
+        let reserves_before = ctx.accounts.reserves();
+        ctx.accounts
+            .execute_buy(side, amount_in, &swap, ctx.bumps.position)?;
 
-        require!(shares_out >= min_shares_out, MarketError::SlippageExceeded);
-        require!(shares_out >= MIN_SHARES_OUTPUT, MarketError::OutputTooSmall);
+        let event = ctx.accounts.trade_event(
+            side,
+            OrderDirection::Buy,
+            amount_in,
+            swap.shares_out,
+            swap.fee,
+            reserves_before,
+        )?;
+        ctx.accounts.record_price(&event)?;
+        emit_cpi!(event);
 
-        // Transfer SOL to vault
-        let cpi_context = CpiContext::new(
-            ctx.accounts.system_program.to_account_info(),
-            anchor_lang::system_program::Transfer {
-                from: ctx.accounts.user.to_account_info(),
-                to: ctx.accounts.vault.to_account_info(),
-            },
+        msg!(
+            "Bought {} {:?} shares for {} lamports",
+            swap.shares_out,
+            side,
+            amount_in
         );
-        anchor_lang::system_program::transfer(cpi_context, amount_in)?;
+        Ok(())
+    }
 
-        // Update pool state
-        // Add fee is effectively added to the pool by not being in reserves math
-        // but we should track it for stats
-        pool.total_fees_collected += fee;
+    /// Buy exactly `shares_out` YES or NO shares using the AMM
+    ///
+    /// # Arguments
+    /// * `side` - Outcome to buy
+    /// * `shares_out` - Exact shares to receive
+    /// * `max_amount_in` - Maximum lamports to spend, fee included
+    pub fn buy_shares_exact_out(
+        ctx: Context<Trade>,
+        side: Outcome,
+        shares_out: u64,
+        max_amount_in: u64,
+    ) -> Result<()> {
+        check_amm_trade(&ctx.accounts.market, &ctx.accounts.config)?;
+        require!(shares_out >= MIN_SHARES_OUTPUT, MarketError::OutputTooSmall);
 
-        // Update reserves
-        match side {
-            Outcome::Yes => {
-                pool.no_reserve = new_reserve_in;
-                pool.yes_reserve = new_reserve_out;
-            }
-            Outcome::No => {
-                pool.yes_reserve = new_reserve_in;
-                pool.no_reserve = new_reserve_out;
-            }
-        }
+        let (amount_in, swap) =
+            calculate_buy_exact_out(&ctx.accounts.pool, &ctx.accounts.market, side, shares_out)?;
 
-        // Update market totals
-        let market = &mut ctx.accounts.market;
-        match side {
-            Outcome::Yes => market.total_yes_shares += shares_out,
-            Outcome::No => market.total_no_shares += shares_out,
-        }
+        require!(amount_in <= max_amount_in, MarketError::SlippageExceeded);
 
-        // Update or create position
-        let position = &mut ctx.accounts.position;
-        if position.user == Pubkey::default() {
-            position.user = ctx.accounts.user.key();
-            position.market = market.key();
-            position.bump = ctx.bumps.position;
-        }
+        let reserves_before = ctx.accounts.reserves();
+        ctx.accounts
+            .execute_buy(side, amount_in, &swap, ctx.bumps.position)?;
 
-        // Update position shares
-        let current_price = get_price_for_side(pool, side)?;
-        match side {
-            Outcome::Yes => {
-                let old_shares = position.yes_shares;
-                let new_shares = old_shares + shares_out;
-                if new_shares > 0 {
-                    position.yes_avg_price = ((position.yes_avg_price as u128 * old_shares as u128
-                        + current_price as u128 * shares_out as u128)
-                        / new_shares as u128) as u64;
-                }
-                position.yes_shares = new_shares;
-            }
-            Outcome::No => {
-                let old_shares = position.no_shares;
-                let new_shares = old_shares + shares_out;
-                if new_shares > 0 {
-                    position.no_avg_price = ((position.no_avg_price as u128 * old_shares as u128
-                        + current_price as u128 * shares_out as u128)
-                        / new_shares as u128) as u64;
-                }
-                position.no_shares = new_shares;
-            }
-        }
+        let event = ctx.accounts.trade_event(
+            side,
+            OrderDirection::Buy,
+            amount_in,
+            shares_out,
+            swap.fee,
+            reserves_before,
+        )?;
+        ctx.accounts.record_price(&event)?;
+        emit_cpi!(event);
 
         msg!(
             "Bought {} {:?} shares for {} lamports",
@@ -396,10 +1138,7 @@ pub mod prediction_market {
         shares_in: u64,
         min_amount_out: u64,
     ) -> Result<()> {
-        require!(
-            ctx.accounts.market.status == MarketStatus::Active,
-            MarketError::MarketNotActive
-        );
+        check_amm_trade(&ctx.accounts.market, &ctx.accounts.config)?;
         require!(shares_in > 0, MarketError::InvalidAmount);
 
         // Verify user has enough shares
@@ -415,85 +1154,83 @@ pub mod prediction_market {
             ),
         }
 
-        let pool = &mut ctx.accounts.pool;
+        let swap = calculate_sell(&ctx.accounts.pool, &ctx.accounts.market, side, shares_in)?;
 
         require!(
-            pool.yes_reserve > 0 && pool.no_reserve > 0,
-            MarketError::PoolNotInitialized
+            swap.amount_out >= min_amount_out,
+            MarketError::SlippageExceeded
+        );
+        require!(
+            swap.amount_out >= MIN_SHARES_OUTPUT,
+            MarketError::OutputTooSmall
         );
 
-        // Calculate output using constant product formula
-        let (reserve_in, reserve_out) = match side {
-            Outcome::Yes => (pool.yes_reserve, pool.no_reserve),
-            Outcome::No => (pool.no_reserve, pool.yes_reserve),
-        };
+        let reserves_before = ctx.accounts.reserves();
+        ctx.accounts
+            .execute_sell(side, shares_in, &swap, ctx.bumps.vault)?;
 
-        let k = reserve_in as u128 * reserve_out as u128;
-        let new_reserve_in = reserve_in + shares_in;
-        let new_reserve_out = (k / new_reserve_in as u128) as u64;
-        let amount_out_before_fee = reserve_out.saturating_sub(new_reserve_out);
+        let event = ctx.accounts.trade_event(
+            side,
+            OrderDirection::Sell,
+            swap.amount_out,
+            shares_in,
+            swap.fee,
+            reserves_before,
+        )?;
+        ctx.accounts.record_price(&event)?;
+        emit_cpi!(event);
 
-        let fee = amount_out_before_fee * LP_FEE_BPS / BASIS_POINTS;
-        let amount_out = amount_out_before_fee - fee;
+        msg!(
+            "Sold {} {:?} shares for {} lamports",
+            shares_in,
+            side,
+            swap.amount_out
+        );
+        Ok(())
+    }
 
-        require!(amount_out >= min_amount_out, MarketError::SlippageExceeded);
+    /// Sell YES or NO shares back to the AMM for exactly `amount_out` lamports
+    ///
+    /// # Arguments
+    /// * `side` - Outcome to sell
+    /// * `amount_out` - Exact lamports to receive, after fees
+    /// * `max_shares_in` - Maximum shares to sell
+    pub fn sell_shares_exact_out(
+        ctx: Context<Trade>,
+        side: Outcome,
+        amount_out: u64,
+        max_shares_in: u64,
+    ) -> Result<()> {
+        check_amm_trade(&ctx.accounts.market, &ctx.accounts.config)?;
         require!(amount_out >= MIN_SHARES_OUTPUT, MarketError::OutputTooSmall);
 
-        // Check vault balance
-        let vault_lamports = ctx.accounts.vault.lamports();
-        require!(
-            vault_lamports >= amount_out,
-            MarketError::InsufficientVaultFunds
-        );
-
-        // Transfer SOL from vault to user
-        let bump = ctx.bumps.vault;
-        let bump_slice = &[bump];
-        let market_key = ctx.accounts.market.key();
-        let seeds = &[VAULT_SEED, market_key.as_ref(), bump_slice];
-        let signer_seeds = &[&seeds[..]];
+        let (shares_in, swap) =
+            calculate_sell_exact_out(&ctx.accounts.pool, &ctx.accounts.market, side, amount_out)?;
 
-        let cpi_context = CpiContext::new_with_signer(
-            ctx.accounts.system_program.to_account_info(),
-            anchor_lang::system_program::Transfer {
-                from: ctx.accounts.vault.to_account_info(),
-                to: ctx.accounts.user.to_account_info(),
-            },
-            signer_seeds,
-        );
-        anchor_lang::system_program::transfer(cpi_context, amount_out)?;
+        require!(shares_in <= max_shares_in, MarketError::SlippageExceeded);
 
-        // Update pool state
-        let pool = &mut ctx.accounts.pool;
-        match side {
-            Outcome::Yes => {
-                pool.yes_reserve = new_reserve_in;
-                pool.no_reserve = new_reserve_out;
-            }
-            Outcome::No => {
-                pool.no_reserve = new_reserve_in;
-                pool.yes_reserve = new_reserve_out;
-            }
-        }
-        pool.total_fees_collected += fee;
+        // Verify user has enough shares
+        let position = &ctx.accounts.position;
+        let shares_held = match side {
+            Outcome::Yes => position.yes_shares,
+            Outcome::No => position.no_shares,
+        };
+        require!(shares_held >= shares_in, MarketError::InsufficientShares);
 
-        // Update market totals
-        let market = &mut ctx.accounts.market;
-        match side {
-            Outcome::Yes => {
-                market.total_yes_shares = market.total_yes_shares.saturating_sub(shares_in)
-            }
-            Outcome::No => {
-                market.total_no_shares = market.total_no_shares.saturating_sub(shares_in)
-            }
-        }
+        let reserves_before = ctx.accounts.reserves();
+        ctx.accounts
+            .execute_sell(side, shares_in, &swap, ctx.bumps.vault)?;
 
-        // Update position
-        let position = &mut ctx.accounts.position;
-        match side {
-            Outcome::Yes => position.yes_shares = position.yes_shares.saturating_sub(shares_in),
-            Outcome::No => position.no_shares = position.no_shares.saturating_sub(shares_in),
-        }
+        let event = ctx.accounts.trade_event(
+            side,
+            OrderDirection::Sell,
+            amount_out,
+            shares_in,
+            swap.fee,
+            reserves_before,
+        )?;
+        ctx.accounts.record_price(&event)?;
+        emit_cpi!(event);
 
         msg!(
             "Sold {} {:?} shares for {} lamports",
@@ -504,375 +1241,5234 @@ pub mod prediction_market {
         Ok(())
     }
 
-    /// Resolve the market using Pyth oracle price feed
-    pub fn resolve_market(ctx: Context<ResolveMarket>) -> Result<()> {
-        let market = &ctx.accounts.market;
+    // ========================================
+    // Price History
+    // ========================================
 
+    /// Start recording YES price candles for a market
+    ///
+    /// Authority only. Once created, every instruction that moves the pool's
+    /// reserves must pass the history and records into it. The account keeps
+    /// the last `MAX_PRICE_CANDLES` candles, overwriting the oldest.
+    ///
+    /// # Arguments
+    /// * `interval` - Seconds covered by each candle
+    pub fn initialize_price_history(
+        ctx: Context<InitializePriceHistory>,
+        interval: i64,
+    ) -> Result<()> {
         require!(
-            market.status == MarketStatus::Active,
+            ctx.accounts.market.status == MarketStatus::Active,
             MarketError::MarketNotActive
         );
         require!(
-            Clock::get()?.unix_timestamp >= market.expiration + RESOLUTION_DELAY,
-            MarketError::MarketNotExpired
+            ctx.accounts.market.authority == ctx.accounts.authority.key(),
+            MarketError::Unauthorized
+        );
+        require!(
+            (MIN_CANDLE_INTERVAL..=MAX_CANDLE_INTERVAL).contains(&interval),
+            MarketError::InvalidCandleInterval
         );
 
-        // Read price from Pyth oracle
-        let price_account_info = &ctx.accounts.pyth_price_account;
-        let price_feed = SolanaPriceAccount::account_info_to_feed(price_account_info)
-            .map_err(|_| MarketError::InvalidOraclePrice)?;
+        let history = &mut ctx.accounts.price_history;
+        history.market = ctx.accounts.market.key();
+        history.interval = interval;
+        history.head = 0;
+        history.candles = Vec::new();
+        history.bump = ctx.bumps.price_history;
+        ctx.accounts.market.has_price_history = true;
 
-        let current_price = price_feed
-            .get_price_no_older_than(
-                Clock::get()?.unix_timestamp,
-                300, // 5 minutes max staleness
-            )
-            .ok_or(MarketError::InvalidOraclePrice)?;
+        msg!("Price history initialized with {}s candles", interval);
+        Ok(())
+    }
 
-        // Check confidence interval
+    // ========================================
+    // Quote Functions
+    // ========================================
+
+    /// Preview `buy_shares` without executing it
+    ///
+    /// Read-only; the quote is returned as return data for `simulateTransaction`.
+    pub fn quote_buy(ctx: Context<Quote>, side: Outcome, amount_in: u64) -> Result<TradeQuote> {
+        check_amm_trade(&ctx.accounts.market, &ctx.accounts.config)?;
+        require!(amount_in > 0, MarketError::InvalidAmount);
+
+        let pool = &ctx.accounts.pool;
+        let swap = calculate_buy(pool, &ctx.accounts.market, side, amount_in)?;
         require!(
-            current_price.conf <= market.max_confidence,
-            MarketError::ConfidenceTooHigh
+            swap.shares_out >= MIN_SHARES_OUTPUT,
+            MarketError::OutputTooSmall
         );
 
-        // Determine outcome
-        let outcome = if current_price.price >= market.strike_price {
-            Outcome::Yes
-        } else {
-            Outcome::No
-        };
+        let mut pool_after = (**pool).clone();
+        let mut market_after = (**ctx.accounts.market).clone();
+        apply_buy(&mut pool_after, &mut market_after, side, &swap);
 
-        // Update market state
-        let market = &mut ctx.accounts.market;
-        market.status = MarketStatus::Resolved;
-        market.outcome = Some(outcome);
-        market.resolution_price = Some(current_price.price);
-        market.resolution_timestamp = Some(Clock::get()?.unix_timestamp);
+        build_trade_quote(
+            pool,
+            &pool_after,
+            side,
+            amount_in,
+            swap.shares_out,
+            swap.fee,
+        )
+    }
+
+    /// Preview `sell_shares` without executing it
+    ///
+    /// Read-only; the quote is returned as return data for `simulateTransaction`.
+    pub fn quote_sell(ctx: Context<Quote>, side: Outcome, shares_in: u64) -> Result<TradeQuote> {
+        check_amm_trade(&ctx.accounts.market, &ctx.accounts.config)?;
+        require!(shares_in > 0, MarketError::InvalidAmount);
+
+        let pool = &ctx.accounts.pool;
+        let swap = calculate_sell(pool, &ctx.accounts.market, side, shares_in)?;
+        require!(
+            swap.amount_out >= MIN_SHARES_OUTPUT,
+            MarketError::OutputTooSmall
+        );
+
+        let mut pool_after = (**pool).clone();
+        let mut market_after = (**ctx.accounts.market).clone();
+        apply_sell(&mut pool_after, &mut market_after, side, shares_in, &swap);
+
+        build_trade_quote(
+            pool,
+            &pool_after,
+            side,
+            shares_in,
+            swap.amount_out,
+            swap.fee,
+        )
+    }
+
+    /// Preview `add_liquidity` without executing it
+    pub fn quote_add_liquidity(ctx: Context<Quote>, amount: u64) -> Result<LiquidityQuote> {
+        check_liquidity_open(&ctx.accounts.market, &ctx.accounts.config)?;
+        require!(amount > 0, MarketError::InvalidAmount);
+
+        let pool = &ctx.accounts.pool;
+        let lp_tokens = calculate_lp_tokens_to_mint(pool, amount);
+
+        Ok(LiquidityQuote {
+            amount,
+            lp_tokens,
+            pool_share_bps: (lp_tokens as u128 * BASIS_POINTS as u128
+                / (pool.lp_token_supply + lp_tokens) as u128) as u64,
+        })
+    }
+
+    /// Preview `remove_liquidity` without executing it
+    pub fn quote_remove_liquidity(ctx: Context<Quote>, lp_tokens: u64) -> Result<LiquidityQuote> {
+        require!(lp_tokens > 0, MarketError::InvalidAmount);
+
+        let pool = &ctx.accounts.pool;
+        let amount = calculate_liquidity_out(pool, lp_tokens)?;
+
+        Ok(LiquidityQuote {
+            amount,
+            lp_tokens,
+            pool_share_bps: (lp_tokens as u128 * BASIS_POINTS as u128
+                / pool.lp_token_supply as u128) as u64,
+        })
+    }
+
+    /// Move shares from the caller's position to another wallet's position
+    ///
+    /// The recipient's position is created if needed. The shares carry their
+    /// cost basis at the sender's average price: it leaves the sender's cost and
+    /// is blended into the recipient's average price.
+    ///
+    /// # Arguments
+    /// * `side` - Outcome to transfer
+    /// * `amount` - Shares to transfer
+    /// * `to` - Recipient wallet
+    pub fn transfer_shares(
+        ctx: Context<TransferShares>,
+        side: Outcome,
+        amount: u64,
+        to: Pubkey,
+    ) -> Result<()> {
+        require!(
+            ctx.accounts.market.status == MarketStatus::Active,
+            MarketError::MarketNotActive
+        );
+        require!(!ctx.accounts.config.paused, MarketError::ProtocolPaused);
+        require!(amount > 0, MarketError::InvalidAmount);
+        require_keys_neq!(to, ctx.accounts.user.key(), MarketError::InvalidPosition);
+
+        let from_position = &mut ctx.accounts.from_position;
+        let (held, avg_price) = match side {
+            Outcome::Yes => (from_position.yes_shares, from_position.yes_avg_price),
+            Outcome::No => (from_position.no_shares, from_position.no_avg_price),
+        };
+        require!(held >= amount, MarketError::InsufficientShares);
+        let moved_cost = shares_cost(amount, avg_price);
+        debit_position(from_position, side, amount);
+        match side {
+            Outcome::Yes => {
+                from_position.yes_cost = from_position.yes_cost.saturating_sub(moved_cost)
+            }
+            Outcome::No => from_position.no_cost = from_position.no_cost.saturating_sub(moved_cost),
+        }
+
+        let to_position = &mut ctx.accounts.to_position;
+        if to_position.user == Pubkey::default() {
+            to_position.user = to;
+            to_position.market = ctx.accounts.market.key();
+            to_position.bump = ctx.bumps.to_position;
+        }
+        credit_position(to_position, side, amount, moved_cost);
+
+        emit_cpi!(SharesTransferred {
+            market: ctx.accounts.market.key(),
+            from: ctx.accounts.user.key(),
+            to,
+            side,
+            shares: amount,
+        });
+
+        msg!("Transferred {} {:?} shares to {}", amount, side, to);
+        Ok(())
+    }
+
+    /// Place a limit order that keepers fill against the AMM once the price crosses
+    ///
+    /// Buy orders escrow `size` lamports, sell orders escrow `size` shares from
+    /// the owner's position. The keeper tip is escrowed alongside either way.
+    ///
+    /// # Arguments
+    /// * `order_id` - Owner-chosen identifier, unique per market
+    /// * `side` - Outcome to trade
+    /// * `direction` - Whether to buy or sell `side`
+    /// * `limit_price` - Worst acceptable average execution price, fees included (scaled by PRICE_DECIMALS)
+    /// * `size` - Lamports to spend (buy) or shares to sell (sell)
+    /// * `expiry` - Unix timestamp after which the order can no longer be filled
+    /// * `keeper_tip` - Lamports paid to the keeper that fills the order
+    #[allow(clippy::too_many_arguments)]
+    pub fn place_limit_order(
+        ctx: Context<PlaceLimitOrder>,
+        order_id: u64,
+        side: Outcome,
+        direction: OrderDirection,
+        limit_price: u64,
+        size: u64,
+        expiry: i64,
+        keeper_tip: u64,
+    ) -> Result<()> {
+        require!(
+            ctx.accounts.market.status == MarketStatus::Active,
+            MarketError::MarketNotActive
+        );
+        require!(!ctx.accounts.config.paused, MarketError::ProtocolPaused);
+        require!(size > 0, MarketError::InvalidAmount);
+        require!(
+            limit_price > 0 && limit_price < PRICE_DECIMALS,
+            MarketError::InvalidLimitPrice
+        );
+        require!(
+            expiry > Clock::get()?.unix_timestamp,
+            MarketError::InvalidExpiration
+        );
+
+        // Escrow collateral (for buys) plus the keeper tip in the order account
+        let escrow = match direction {
+            OrderDirection::Buy => size + keeper_tip,
+            OrderDirection::Sell => keeper_tip,
+        };
+        if escrow > 0 {
+            let cpi_context = CpiContext::new(
+                ctx.accounts.system_program.to_account_info(),
+                anchor_lang::system_program::Transfer {
+                    from: ctx.accounts.owner.to_account_info(),
+                    to: ctx.accounts.order.to_account_info(),
+                },
+            );
+            anchor_lang::system_program::transfer(cpi_context, escrow)?;
+        }
+
+        // Create the position up front so fills always have somewhere to land
+        let position = &mut ctx.accounts.position;
+        if position.user == Pubkey::default() {
+            position.user = ctx.accounts.owner.key();
+            position.market = ctx.accounts.market.key();
+            position.bump = ctx.bumps.position;
+        }
+
+        position.open_orders += 1;
+
+        // Escrow shares for sells by moving them out of the position
+        if direction == OrderDirection::Sell {
+            let shares_held = match side {
+                Outcome::Yes => &mut position.yes_shares,
+                Outcome::No => &mut position.no_shares,
+            };
+            require!(*shares_held >= size, MarketError::InsufficientShares);
+            *shares_held -= size;
+        }
+
+        let order = &mut ctx.accounts.order;
+        order.owner = ctx.accounts.owner.key();
+        order.market = ctx.accounts.market.key();
+        order.order_id = order_id;
+        order.side = side;
+        order.direction = direction;
+        order.limit_price = limit_price;
+        order.size = size;
+        order.expiry = expiry;
+        order.keeper_tip = keeper_tip;
+        order.created_at = Clock::get()?.unix_timestamp;
+        order.bump = ctx.bumps.order;
+
+        emit_cpi!(LimitOrderPlaced {
+            market: order.market,
+            owner: order.owner,
+            order_id,
+            side,
+            direction,
+            limit_price,
+            size,
+            expiry,
+        });
 
         msg!(
-            "Market resolved: {:?} (price: {}, strike: {})",
-            outcome,
-            current_price.price,
-            market.strike_price
+            "Limit order {} placed: {:?} {} {:?} at {}",
+            order_id,
+            direction,
+            size,
+            side,
+            limit_price
         );
         Ok(())
     }
 
-    /// Claim winnings after market resolution
-    pub fn claim_winnings(ctx: Context<ClaimWinnings>) -> Result<()> {
-        let market = &ctx.accounts.market;
-        let position = &ctx.accounts.position;
+    /// Fill a limit order against the AMM (permissionless keeper crank)
+    ///
+    /// Executes once the whole order fills at an average price, fees included,
+    /// at or below the limit for buys, or at or above it for sells. The keeper receives the escrowed tip and the
+    /// order account is closed back to its owner.
+    pub fn fill_limit_order(ctx: Context<FillLimitOrder>) -> Result<()> {
+        check_amm_trade(&ctx.accounts.market, &ctx.accounts.config)?;
 
+        let order = &ctx.accounts.order;
         require!(
-            market.status == MarketStatus::Resolved,
-            MarketError::MarketNotResolved
+            Clock::get()?.unix_timestamp <= order.expiry,
+            MarketError::OrderExpired
         );
+
+        let side = order.side;
+        let direction = order.direction;
+        let size = order.size;
+        let keeper_tip = order.keeper_tip;
+        let limit_price = order.limit_price;
+        let reserves_before = (ctx.accounts.pool.yes_reserve, ctx.accounts.pool.no_reserve);
+
+        // The limit applies to the average execution price, fees included,
+        // not the spot price, so the fill cannot be sandwiched past it
+        let (amount, shares, fee) = match direction {
+            OrderDirection::Buy => {
+                let swap = calculate_buy(&ctx.accounts.pool, &ctx.accounts.market, side, size)?;
+                require!(
+                    shares_cost(swap.shares_out, limit_price) >= size,
+                    MarketError::LimitPriceNotReached
+                );
+                require!(
+                    swap.shares_out >= MIN_SHARES_OUTPUT,
+                    MarketError::OutputTooSmall
+                );
+
+                // Move escrowed collateral into the vault
+                ctx.accounts.order.sub_lamports(size)?;
+                ctx.accounts.vault.add_lamports(size)?;
+
+                let pool = &mut ctx.accounts.pool;
+                apply_buy(pool, &mut ctx.accounts.market, side, &swap);
+
+                let position = &mut ctx.accounts.position;
+                credit_position(position, side, swap.shares_out, size);
+                position.fees_paid += swap.fee;
+
+                msg!(
+                    "Limit order filled: bought {} {:?} shares for {} lamports",
+                    swap.shares_out,
+                    side,
+                    size
+                );
+                (size, swap.shares_out, swap.fee)
+            }
+            OrderDirection::Sell => {
+                let swap = calculate_sell(&ctx.accounts.pool, &ctx.accounts.market, side, size)?;
+                require!(
+                    swap.amount_out >= shares_cost(size, limit_price),
+                    MarketError::LimitPriceNotReached
+                );
+                require!(
+                    swap.amount_out >= MIN_SHARES_OUTPUT,
+                    MarketError::OutputTooSmall
+                );
+                require!(
+                    ctx.accounts.vault.lamports() >= swap.amount_out,
+                    MarketError::InsufficientVaultFunds
+                );
+
+                transfer_from_vault(
+                    &ctx.accounts.vault,
+                    &ctx.accounts.owner.to_account_info(),
+                    &ctx.accounts.system_program.to_account_info(),
+                    &ctx.accounts.market.key(),
+                    ctx.bumps.vault,
+                    swap.amount_out,
+                )?;
+
+                apply_sell(
+                    &mut ctx.accounts.pool,
+                    &mut ctx.accounts.market,
+                    side,
+                    size,
+                    &swap,
+                );
+                realize_proceeds(
+                    &mut ctx.accounts.position,
+                    side,
+                    size,
+                    swap.amount_out,
+                    swap.fee,
+                );
+
+                msg!(
+                    "Limit order filled: sold {} {:?} shares for {} lamports",
+                    size,
+                    side,
+                    swap.amount_out
+                );
+                (swap.amount_out, size, swap.fee)
+            }
+        };
+
+        let event = trade_executed(
+            ctx.accounts.market.key(),
+            ctx.accounts.owner.key(),
+            &ctx.accounts.pool,
+            reserves_before,
+            side,
+            direction,
+            amount,
+            shares,
+            fee,
+        )?;
+        record_price(
+            &ctx.accounts.market,
+            &mut ctx.accounts.price_history,
+            &event,
+        )?;
+        emit_cpi!(event);
+
+        // Pay the keeper; the rest of the order account returns to the owner on close
+        if keeper_tip > 0 {
+            ctx.accounts.order.sub_lamports(keeper_tip)?;
+            ctx.accounts.keeper.add_lamports(keeper_tip)?;
+        }
+        let position = &mut ctx.accounts.position;
+        position.open_orders = position.open_orders.saturating_sub(1);
+
+        Ok(())
+    }
+
+    /// Cancel a limit order, returning escrowed shares and lamports to the owner
+    pub fn cancel_limit_order(ctx: Context<CancelLimitOrder>) -> Result<()> {
+        let order = &ctx.accounts.order;
+
+        // Escrowed lamports come back when the order account is closed
+        let position = &mut ctx.accounts.position;
+        if order.direction == OrderDirection::Sell {
+            match order.side {
+                Outcome::Yes => position.yes_shares += order.size,
+                Outcome::No => position.no_shares += order.size,
+            }
+        }
+        position.open_orders = position.open_orders.saturating_sub(1);
+
+        msg!("Limit order {} cancelled", order.order_id);
+        Ok(())
+    }
+
+    /// Attach a stop-loss or take-profit trigger to the caller's position
+    ///
+    /// Shares stay in the position until a keeper executes the trigger; the
+    /// keeper tip is escrowed in the trigger account.
+    ///
+    /// # Arguments
+    /// * `trigger_id` - Owner-chosen identifier, unique per position
+    /// * `side` - Outcome whose shares are sold when triggered
+    /// * `kind` - Stop-loss (fires at or below) or take-profit (fires at or above)
+    /// * `trigger_price` - Pool price that fires the trigger (scaled by PRICE_DECIMALS)
+    /// * `shares` - Shares to sell when triggered
+    /// * `min_amount_out` - Minimum lamports accepted for the sale
+    /// * `keeper_tip` - Lamports paid to the keeper that executes the trigger
+    #[allow(clippy::too_many_arguments)]
+    pub fn create_trigger(
+        ctx: Context<CreateTrigger>,
+        trigger_id: u64,
+        side: Outcome,
+        kind: TriggerKind,
+        trigger_price: u64,
+        shares: u64,
+        min_amount_out: u64,
+        keeper_tip: u64,
+    ) -> Result<()> {
         require!(
-            position.user == ctx.accounts.user.key(),
-            MarketError::InvalidPosition
+            ctx.accounts.market.status == MarketStatus::Active,
+            MarketError::MarketNotActive
+        );
+        require!(!ctx.accounts.config.paused, MarketError::ProtocolPaused);
+        require!(shares > 0, MarketError::InvalidAmount);
+        require!(
+            trigger_price > 0 && trigger_price < PRICE_DECIMALS,
+            MarketError::InvalidLimitPrice
         );
-        require!(!position.claimed, MarketError::AlreadyClaimed);
-
-        let outcome = market.outcome.ok_or(MarketError::MarketNotResolved)?;
 
-        // Calculate winnings based on outcome
-        let winning_shares = match outcome {
+        let position = &ctx.accounts.position;
+        let shares_held = match side {
             Outcome::Yes => position.yes_shares,
             Outcome::No => position.no_shares,
         };
+        require!(shares_held >= shares, MarketError::InsufficientShares);
+
+        if keeper_tip > 0 {
+            let cpi_context = CpiContext::new(
+                ctx.accounts.system_program.to_account_info(),
+                anchor_lang::system_program::Transfer {
+                    from: ctx.accounts.owner.to_account_info(),
+                    to: ctx.accounts.trigger.to_account_info(),
+                },
+            );
+            anchor_lang::system_program::transfer(cpi_context, keeper_tip)?;
+        }
 
-        require!(winning_shares > 0, MarketError::NoWinnings);
+        let trigger = &mut ctx.accounts.trigger;
+        trigger.owner = ctx.accounts.owner.key();
+        trigger.market = ctx.accounts.market.key();
+        trigger.position = ctx.accounts.position.key();
+        trigger.trigger_id = trigger_id;
+        trigger.side = side;
+        trigger.kind = kind;
+        trigger.trigger_price = trigger_price;
+        trigger.shares = shares;
+        trigger.min_amount_out = min_amount_out;
+        trigger.keeper_tip = keeper_tip;
+        trigger.created_at = Clock::get()?.unix_timestamp;
+        trigger.bump = ctx.bumps.trigger;
 
-        // Each winning share is worth 1 unit of collateral (1 lamport per share unit)
-        let payout = winning_shares;
+        msg!(
+            "{:?} trigger {} set: sell {} {:?} shares at {}",
+            kind,
+            trigger_id,
+            shares,
+            side,
+            trigger_price
+        );
+        Ok(())
+    }
 
-        // Check vault balance
-        let vault_lamports = ctx.accounts.vault.lamports();
+    /// Execute a stop-loss or take-profit trigger (permissionless keeper crank)
+    ///
+    /// Sells through the same constant product math as `sell_shares`, paying the
+    /// owner and tipping the keeper. The trigger account is closed to the owner.
+    pub fn execute_trigger(ctx: Context<ExecuteTrigger>) -> Result<()> {
+        check_amm_trade(&ctx.accounts.market, &ctx.accounts.config)?;
+
+        let trigger = &ctx.accounts.trigger;
+        let side = trigger.side;
+        let shares = trigger.shares;
+        let keeper_tip = trigger.keeper_tip;
+
+        let price = get_price_for_side(&ctx.accounts.pool, side)?;
+        let fired = match trigger.kind {
+            TriggerKind::StopLoss => price <= trigger.trigger_price,
+            TriggerKind::TakeProfit => price >= trigger.trigger_price,
+        };
+        require!(fired, MarketError::TriggerNotReached);
+
+        let position = &ctx.accounts.position;
+        let shares_held = match side {
+            Outcome::Yes => position.yes_shares,
+            Outcome::No => position.no_shares,
+        };
+        require!(shares_held >= shares, MarketError::InsufficientShares);
+
+        let reserves_before = (ctx.accounts.pool.yes_reserve, ctx.accounts.pool.no_reserve);
+        let swap = calculate_sell(&ctx.accounts.pool, &ctx.accounts.market, side, shares)?;
         require!(
-            vault_lamports >= payout,
+            swap.amount_out >= trigger.min_amount_out,
+            MarketError::SlippageExceeded
+        );
+        require!(
+            swap.amount_out >= MIN_SHARES_OUTPUT,
+            MarketError::OutputTooSmall
+        );
+        require!(
+            ctx.accounts.vault.lamports() >= swap.amount_out,
             MarketError::InsufficientVaultFunds
         );
 
-        // Transfer winnings from vault
-        let bump = ctx.bumps.vault;
-        let bump_slice = &[bump];
-        let market_key = ctx.accounts.market.key();
-        let seeds = &[VAULT_SEED, market_key.as_ref(), bump_slice];
-        let signer_seeds = &[&seeds[..]];
+        transfer_from_vault(
+            &ctx.accounts.vault,
+            &ctx.accounts.owner.to_account_info(),
+            &ctx.accounts.system_program.to_account_info(),
+            &ctx.accounts.market.key(),
+            ctx.bumps.vault,
+            swap.amount_out,
+        )?;
 
-        let cpi_context = CpiContext::new_with_signer(
-            ctx.accounts.system_program.to_account_info(),
-            anchor_lang::system_program::Transfer {
-                from: ctx.accounts.vault.to_account_info(),
-                to: ctx.accounts.user.to_account_info(),
-            },
-            signer_seeds,
+        apply_sell(
+            &mut ctx.accounts.pool,
+            &mut ctx.accounts.market,
+            side,
+            shares,
+            &swap,
         );
-        anchor_lang::system_program::transfer(cpi_context, payout)?;
-
-        // Mark position as claimed
         let position = &mut ctx.accounts.position;
-        position.claimed = true;
+        realize_proceeds(position, side, shares, swap.amount_out, swap.fee);
+        debit_position(position, side, shares);
+
+        let event = trade_executed(
+            ctx.accounts.market.key(),
+            ctx.accounts.owner.key(),
+            &ctx.accounts.pool,
+            reserves_before,
+            side,
+            OrderDirection::Sell,
+            swap.amount_out,
+            shares,
+            swap.fee,
+        )?;
+        record_price(
+            &ctx.accounts.market,
+            &mut ctx.accounts.price_history,
+            &event,
+        )?;
+        emit_cpi!(event);
+
+        if keeper_tip > 0 {
+            ctx.accounts.trigger.sub_lamports(keeper_tip)?;
+            ctx.accounts.keeper.add_lamports(keeper_tip)?;
+        }
 
         msg!(
-            "Claimed {} lamports for {} winning shares",
-            payout,
-            winning_shares
+            "Trigger executed: sold {} {:?} shares for {} lamports",
+            shares,
+            side,
+            swap.amount_out
         );
         Ok(())
     }
 
-    /// Cancel a market that has not been resolved
-    pub fn cancel_market(ctx: Context<CancelMarket>) -> Result<()> {
-        let market = &mut ctx.accounts.market;
+    /// Remove a trigger from a position, refunding the keeper tip
+    pub fn cancel_trigger(ctx: Context<CancelTrigger>) -> Result<()> {
+        msg!("Trigger {} cancelled", ctx.accounts.trigger.trigger_id);
+        Ok(())
+    }
+
+    // ========================================
+    // Order Book Functions
+    // ========================================
+
+    /// Create the order book for a market
+    ///
+    /// The book holds YES bids and asks; NO trades are matched against the
+    /// complementary YES order. Meant to be delegated alongside the market so
+    /// matching runs on the ephemeral rollup.
+    pub fn initialize_order_book(ctx: Context<InitializeOrderBook>) -> Result<()> {
         require!(
-            market.status == MarketStatus::Active,
+            ctx.accounts.market.status == MarketStatus::Active,
             MarketError::MarketNotActive
         );
-        require!(
-            market.authority == ctx.accounts.authority.key(),
-            MarketError::Unauthorized
-        );
 
-        market.status = MarketStatus::Cancelled;
-        msg!("Market cancelled by authority");
+        let book = &mut ctx.accounts.order_book;
+        book.market = ctx.accounts.market.key();
+        book.next_order_id = 0;
+        book.bids = Vec::new();
+        book.asks = Vec::new();
+        book.bump = ctx.bumps.order_book;
+
+        msg!("Order book initialized");
         Ok(())
     }
 
-    // ========================================
-    // Ephemeral Rollup Functions
-    // ========================================
-
-    /// Delegate market and pool to ephemeral rollup for high-speed trading
-    pub fn delegate_market(ctx: Context<DelegateMarket>) -> Result<()> {
+    /// Rest a YES bid or ask on the order book
+    ///
+    /// Bids escrow `size * price` lamports in the book, asks escrow `size` YES
+    /// shares from the owner's position. Orders that would cross the book are
+    /// rejected; takers match through `buy_shares_routed`/`sell_shares_routed`.
+    ///
+    /// Only orders with size remaining count toward `MAX_BOOK_ORDERS`. Filled
+    /// orders keep their slot until settled; when they fill the side, pass
+    /// `(position, owner)` pairs in `remaining_accounts` to settle and evict them.
+    ///
+    /// # Arguments
+    /// * `book_side` - Bid to buy YES or ask to sell YES
+    /// * `price` - YES price (scaled by PRICE_DECIMALS)
+    /// * `size` - YES shares
+    pub fn place_book_order<'info>(
+        ctx: Context<'_, '_, 'info, 'info, PlaceBookOrder<'info>>,
+        book_side: BookSide,
+        price: u64,
+        size: u64,
+    ) -> Result<()> {
         require!(
             ctx.accounts.market.status == MarketStatus::Active,
             MarketError::MarketNotActive
         );
+        require!(!ctx.accounts.config.paused, MarketError::ProtocolPaused);
+        require!(size >= MIN_SHARES_OUTPUT, MarketError::OutputTooSmall);
+        require!(
+            price > 0 && price < PRICE_DECIMALS,
+            MarketError::InvalidLimitPrice
+        );
+
+        let book = &ctx.accounts.order_book;
+        let (resting, crosses) = match book_side {
+            BookSide::Bid => (
+                book.bids.iter().filter(|bid| bid.remaining > 0).count(),
+                book.asks
+                    .iter()
+                    .any(|ask| ask.remaining > 0 && ask.price <= price),
+            ),
+            BookSide::Ask => (
+                book.asks.iter().filter(|ask| ask.remaining > 0).count(),
+                book.bids
+                    .iter()
+                    .any(|bid| bid.remaining > 0 && bid.price >= price),
+            ),
+        };
+        require!(resting < MAX_BOOK_ORDERS, MarketError::OrderBookFull);
+        require!(!crosses, MarketError::OrderWouldCross);
+
+        require!(
+            ctx.remaining_accounts.len() % 2 == 0,
+            MarketError::InvalidBookAccounts
+        );
+        let market_key = ctx.accounts.market.key();
+        let own_position = ctx.accounts.position.key();
+        for accounts in ctx.remaining_accounts.chunks(2) {
+            evict_filled_book_order(
+                &mut ctx.accounts.order_book,
+                &market_key,
+                &own_position,
+                book_side,
+                &accounts[0],
+                &accounts[1],
+            )?;
+        }
+        let stored = match book_side {
+            BookSide::Bid => ctx.accounts.order_book.bids.len(),
+            BookSide::Ask => ctx.accounts.order_book.asks.len(),
+        };
+        require!(stored < MAX_BOOK_ORDERS, MarketError::BookNeedsSettlement);
+
+        // Create the position up front so fills can be settled into it
+        let position = &mut ctx.accounts.position;
+        if position.user == Pubkey::default() {
+            position.user = ctx.accounts.owner.key();
+            position.market = ctx.accounts.market.key();
+            position.bump = ctx.bumps.position;
+        }
+        position.open_orders += 1;
+
+        let escrow = match book_side {
+            BookSide::Bid => {
+                let escrow = (size as u128 * price as u128).div_ceil(PRICE_DECIMALS as u128) as u64;
+                let cpi_context = CpiContext::new(
+                    ctx.accounts.system_program.to_account_info(),
+                    anchor_lang::system_program::Transfer {
+                        from: ctx.accounts.owner.to_account_info(),
+                        to: ctx.accounts.order_book.to_account_info(),
+                    },
+                );
+                anchor_lang::system_program::transfer(cpi_context, escrow)?;
+                escrow
+            }
+            BookSide::Ask => {
+                require!(position.yes_shares >= size, MarketError::InsufficientShares);
+                position.yes_shares -= size;
+                0
+            }
+        };
+
+        let book = &mut ctx.accounts.order_book;
+        let order = BookOrder {
+            order_id: book.next_order_id,
+            owner: ctx.accounts.owner.key(),
+            price,
+            remaining: size,
+            filled: 0,
+            escrow,
+            placed_at: Clock::get()?.unix_timestamp,
+        };
+        book.next_order_id += 1;
+
+        // Price-time priority: insert behind every order at the same or a better price
+        match book_side {
+            BookSide::Bid => {
+                let index = book
+                    .bids
+                    .iter()
+                    .position(|bid| bid.price < price)
+                    .unwrap_or(book.bids.len());
+                book.bids.insert(index, order);
+            }
+            BookSide::Ask => {
+                let index = book
+                    .asks
+                    .iter()
+                    .position(|ask| ask.price > price)
+                    .unwrap_or(book.asks.len());
+                book.asks.insert(index, order);
+            }
+        }
+
+        emit_cpi!(BookOrderPlaced {
+            market: ctx.accounts.market.key(),
+            owner: order.owner,
+            order_id: order.order_id,
+            book_side,
+            price,
+            size,
+        });
+
+        msg!(
+            "Book order {} placed: {:?} {} YES at {}",
+            order.order_id,
+            book_side,
+            size,
+            price
+        );
+        Ok(())
+    }
+
+    /// Collect the proceeds of a resting order's fills
+    ///
+    /// Bids receive their filled YES shares, asks their filled lamports. Fully
+    /// filled orders are removed from the book.
+    pub fn settle_book_order(ctx: Context<ManageBookOrder>, order_id: u64) -> Result<()> {
+        let (book_side, index) = find_book_order(
+            &ctx.accounts.order_book,
+            order_id,
+            &ctx.accounts.owner.key(),
+        )?;
+        settle_book_fills(
+            &mut ctx.accounts.order_book,
+            &mut ctx.accounts.position,
+            &ctx.accounts.owner.to_account_info(),
+            book_side,
+            index,
+        )?;
+
+        let book = &mut ctx.accounts.order_book;
+        let order = match book_side {
+            BookSide::Bid => book.bids[index],
+            BookSide::Ask => book.asks[index],
+        };
+        if order.remaining == 0 {
+            if order.escrow > 0 {
+                book.sub_lamports(order.escrow)?;
+                ctx.accounts.owner.add_lamports(order.escrow)?;
+            }
+            match book_side {
+                BookSide::Bid => book.bids.remove(index),
+                BookSide::Ask => book.asks.remove(index),
+            };
+            let position = &mut ctx.accounts.position;
+            position.open_orders = position.open_orders.saturating_sub(1);
+        }
+
+        msg!("Book order {} settled", order_id);
+        Ok(())
+    }
+
+    /// Cancel a resting order, settling its fills and returning its escrow
+    pub fn cancel_book_order(ctx: Context<ManageBookOrder>, order_id: u64) -> Result<()> {
+        let (book_side, index) = find_book_order(
+            &ctx.accounts.order_book,
+            order_id,
+            &ctx.accounts.owner.key(),
+        )?;
+        settle_book_fills(
+            &mut ctx.accounts.order_book,
+            &mut ctx.accounts.position,
+            &ctx.accounts.owner.to_account_info(),
+            book_side,
+            index,
+        )?;
+
+        let book = &mut ctx.accounts.order_book;
+        let order = match book_side {
+            BookSide::Bid => book.bids.remove(index),
+            BookSide::Ask => book.asks.remove(index),
+        };
+        if order.escrow > 0 {
+            book.sub_lamports(order.escrow)?;
+            ctx.accounts.owner.add_lamports(order.escrow)?;
+        }
+        let position = &mut ctx.accounts.position;
+        if book_side == BookSide::Ask {
+            position.yes_shares += order.remaining;
+        }
+        position.open_orders = position.open_orders.saturating_sub(1);
+
+        msg!("Book order {} cancelled", order_id);
+        Ok(())
+    }
+
+    /// Buy YES or NO shares, filling from the order book before the AMM
+    ///
+    /// YES buys take resting asks and NO buys take resting bids (minting a
+    /// complete set with the bidder) while they beat the pool price. The rest
+    /// is swapped against the AMM; a remainder too small for the AMM is left
+    /// with the trader.
+    pub fn buy_shares_routed(
+        ctx: Context<RoutedTrade>,
+        side: Outcome,
+        amount_in: u64,
+        min_shares_out: u64,
+    ) -> Result<()> {
+        check_amm_trade(&ctx.accounts.market, &ctx.accounts.config)?;
+        require!(amount_in > 0, MarketError::InvalidAmount);
+
+        let reserves_before = (ctx.accounts.pool.yes_reserve, ctx.accounts.pool.no_reserve);
+        let amm_price = get_price_for_side(&ctx.accounts.pool, side)?;
+        let book = &mut ctx.accounts.order_book;
+
+        let mut budget = amount_in;
+        let mut book_shares = 0u64;
+        let mut book_cost = 0u64;
+        // Taker lamports owed to ask makers, held in the book until they settle
+        let mut to_makers = 0u64;
+        // Complete sets minted with bidders: taker and bidder escrow halves
+        let mut minted = 0u64;
+        let mut taker_mint_cost = 0u64;
+        let mut escrow_mint_cost = 0u64;
+
+        match side {
+            Outcome::Yes => {
+                for ask in book.asks.iter_mut() {
+                    if ask.remaining == 0 {
+                        continue;
+                    }
+                    if budget == 0 || ask.price > amm_price {
+                        break;
+                    }
+                    let affordable =
+                        (budget as u128 * PRICE_DECIMALS as u128 / ask.price as u128) as u64;
+                    let shares = ask.remaining.min(affordable);
+                    if shares == 0 {
+                        break;
+                    }
+                    let cost = (shares as u128 * ask.price as u128).div_ceil(PRICE_DECIMALS as u128)
+                        as u64;
+
+                    ask.remaining -= shares;
+                    ask.filled += cost;
+                    budget -= cost;
+                    to_makers += cost;
+                    book_shares += shares;
+                    book_cost += cost;
+                }
+            }
+            Outcome::No => {
+                for bid in book.bids.iter_mut() {
+                    if bid.remaining == 0 {
+                        continue;
+                    }
+                    let price = PRICE_DECIMALS - bid.price;
+                    if budget == 0 || price > amm_price {
+                        break;
+                    }
+                    let affordable =
+                        (budget as u128 * PRICE_DECIMALS as u128 / price as u128) as u64;
+                    let shares = bid.remaining.min(affordable);
+                    if shares == 0 {
+                        break;
+                    }
+                    let taker_cost =
+                        (shares as u128 * price as u128).div_ceil(PRICE_DECIMALS as u128) as u64;
+                    let maker_cost = shares - taker_cost;
+
+                    bid.remaining -= shares;
+                    bid.escrow -= maker_cost;
+                    bid.filled += shares;
+                    budget -= taker_cost;
+                    minted += shares;
+                    taker_mint_cost += taker_cost;
+                    escrow_mint_cost += maker_cost;
+                    book_shares += shares;
+                    book_cost += taker_cost;
+                }
+            }
+        }
+
+        // Route whatever the book could not fill to the AMM
+        let mut amm_swap = None;
+        if budget > 0 {
+            let swap = calculate_buy(&ctx.accounts.pool, &ctx.accounts.market, side, budget)?;
+            if swap.shares_out >= MIN_SHARES_OUTPUT {
+                amm_swap = Some((budget, swap));
+            }
+        }
+        let amm_amount_in = amm_swap.as_ref().map_or(0, |(amount, _)| *amount);
+        let amm_shares = amm_swap.as_ref().map_or(0, |(_, swap)| swap.shares_out);
+        let amm_fee = amm_swap.as_ref().map_or(0, |(_, swap)| swap.fee);
+
+        let total_shares = book_shares + amm_shares;
+        require!(total_shares > 0, MarketError::OutputTooSmall);
+        require!(
+            total_shares >= min_shares_out,
+            MarketError::SlippageExceeded
+        );
+
+        // Move collateral: maker proceeds into the book, minted sets and AMM input into the vault
+        if to_makers > 0 {
+            let cpi_context = CpiContext::new(
+                ctx.accounts.system_program.to_account_info(),
+                anchor_lang::system_program::Transfer {
+                    from: ctx.accounts.user.to_account_info(),
+                    to: ctx.accounts.order_book.to_account_info(),
+                },
+            );
+            anchor_lang::system_program::transfer(cpi_context, to_makers)?;
+        }
+        let to_vault = taker_mint_cost + amm_amount_in;
+        if to_vault > 0 {
+            let cpi_context = CpiContext::new(
+                ctx.accounts.system_program.to_account_info(),
+                anchor_lang::system_program::Transfer {
+                    from: ctx.accounts.user.to_account_info(),
+                    to: ctx.accounts.vault.to_account_info(),
+                },
+            );
+            anchor_lang::system_program::transfer(cpi_context, to_vault)?;
+        }
+        if escrow_mint_cost > 0 {
+            ctx.accounts.order_book.sub_lamports(escrow_mint_cost)?;
+            ctx.accounts.vault.add_lamports(escrow_mint_cost)?;
+        }
+
+        let market = &mut ctx.accounts.market;
+        market.total_yes_shares += minted;
+        market.total_no_shares += minted;
+        market.trading_started = true;
+
+        // Update or create position
+        let position = &mut ctx.accounts.position;
+        if position.user == Pubkey::default() {
+            position.user = ctx.accounts.user.key();
+            position.market = market.key();
+            position.bump = ctx.bumps.position;
+        }
+        if book_shares > 0 {
+            credit_position(position, side, book_shares, book_cost);
+        }
+        if let Some((amount, swap)) = amm_swap {
+            apply_buy(&mut ctx.accounts.pool, market, side, &swap);
+            credit_position(position, side, swap.shares_out, amount);
+            position.fees_paid += swap.fee;
+        }
+
+        let event = trade_executed(
+            ctx.accounts.market.key(),
+            ctx.accounts.user.key(),
+            &ctx.accounts.pool,
+            reserves_before,
+            side,
+            OrderDirection::Buy,
+            book_cost + amm_amount_in,
+            total_shares,
+            amm_fee,
+        )?;
+        record_price(
+            &ctx.accounts.market,
+            &mut ctx.accounts.price_history,
+            &event,
+        )?;
+        emit_cpi!(event);
+
+        msg!(
+            "Bought {} {:?} shares ({} from book, {} from AMM) for {} lamports",
+            total_shares,
+            side,
+            book_shares,
+            amm_shares,
+            book_cost + amm_amount_in
+        );
+        Ok(())
+    }
+
+    /// Sell YES or NO shares, filling against the order book before the AMM
+    ///
+    /// YES sells hit resting bids and NO sells hit resting asks (merging a
+    /// complete set with the asker) while they beat the pool price. The rest
+    /// is swapped against the AMM; a remainder too small for the AMM stays in
+    /// the trader's position.
+    pub fn sell_shares_routed(
+        ctx: Context<RoutedTrade>,
+        side: Outcome,
+        shares_in: u64,
+        min_amount_out: u64,
+    ) -> Result<()> {
+        check_amm_trade(&ctx.accounts.market, &ctx.accounts.config)?;
+        require!(shares_in > 0, MarketError::InvalidAmount);
+
+        let shares_held = match side {
+            Outcome::Yes => ctx.accounts.position.yes_shares,
+            Outcome::No => ctx.accounts.position.no_shares,
+        };
+        require!(shares_held >= shares_in, MarketError::InsufficientShares);
+
+        let reserves_before = (ctx.accounts.pool.yes_reserve, ctx.accounts.pool.no_reserve);
+        let amm_price = get_price_for_side(&ctx.accounts.pool, side)?;
+        let book = &mut ctx.accounts.order_book;
+
+        let mut left = shares_in;
+        // Bid escrow paid straight to the taker
+        let mut from_escrow = 0u64;
+        // Complete sets merged with askers: vault pays the taker and the asker
+        let mut merged = 0u64;
+        let mut vault_to_taker = 0u64;
+        let mut vault_to_makers = 0u64;
+
+        match side {
+            Outcome::Yes => {
+                for bid in book.bids.iter_mut() {
+                    if bid.remaining == 0 {
+                        continue;
+                    }
+                    if left == 0 || bid.price < amm_price {
+                        break;
+                    }
+                    let shares = bid.remaining.min(left);
+                    let proceeds =
+                        (shares as u128 * bid.price as u128 / PRICE_DECIMALS as u128) as u64;
+
+                    bid.remaining -= shares;
+                    bid.escrow -= proceeds;
+                    bid.filled += shares;
+                    left -= shares;
+                    from_escrow += proceeds;
+                }
+            }
+            Outcome::No => {
+                for ask in book.asks.iter_mut() {
+                    if ask.remaining == 0 {
+                        continue;
+                    }
+                    let price = PRICE_DECIMALS - ask.price;
+                    if left == 0 || price < amm_price {
+                        break;
+                    }
+                    let shares = ask.remaining.min(left);
+                    let taker_proceeds =
+                        (shares as u128 * price as u128 / PRICE_DECIMALS as u128) as u64;
+                    let maker_proceeds = shares - taker_proceeds;
+
+                    ask.remaining -= shares;
+                    ask.filled += maker_proceeds;
+                    left -= shares;
+                    merged += shares;
+                    vault_to_taker += taker_proceeds;
+                    vault_to_makers += maker_proceeds;
+                }
+            }
+        }
+
+        // Route whatever the book could not fill to the AMM
+        let mut amm_swap = None;
+        if left > 0 {
+            let swap = calculate_sell(&ctx.accounts.pool, &ctx.accounts.market, side, left)?;
+            if swap.amount_out >= MIN_SHARES_OUTPUT {
+                amm_swap = Some((left, swap));
+            }
+        }
+        let amm_shares_in = amm_swap.as_ref().map_or(0, |(shares, _)| *shares);
+        let amm_amount_out = amm_swap.as_ref().map_or(0, |(_, swap)| swap.amount_out);
+
+        let shares_sold = shares_in - left + amm_shares_in;
+        let amount_out = from_escrow + vault_to_taker + amm_amount_out;
+        require!(amount_out > 0, MarketError::OutputTooSmall);
+        require!(amount_out >= min_amount_out, MarketError::SlippageExceeded);
+
+        // Check vault balance
+        let from_vault = vault_to_taker + vault_to_makers + amm_amount_out;
+        require!(
+            ctx.accounts.vault.lamports() >= from_vault,
+            MarketError::InsufficientVaultFunds
+        );
+
+        if from_escrow > 0 {
+            ctx.accounts.order_book.sub_lamports(from_escrow)?;
+            ctx.accounts.user.add_lamports(from_escrow)?;
+        }
+        if vault_to_taker + amm_amount_out > 0 {
+            transfer_from_vault(
+                &ctx.accounts.vault,
+                &ctx.accounts.user.to_account_info(),
+                &ctx.accounts.system_program.to_account_info(),
+                &ctx.accounts.market.key(),
+                ctx.bumps.vault,
+                vault_to_taker + amm_amount_out,
+            )?;
+        }
+        if vault_to_makers > 0 {
+            transfer_from_vault(
+                &ctx.accounts.vault,
+                &ctx.accounts.order_book.to_account_info(),
+                &ctx.accounts.system_program.to_account_info(),
+                &ctx.accounts.market.key(),
+                ctx.bumps.vault,
+                vault_to_makers,
+            )?;
+        }
+
+        let market = &mut ctx.accounts.market;
+        market.total_yes_shares = market.total_yes_shares.saturating_sub(merged);
+        market.total_no_shares = market.total_no_shares.saturating_sub(merged);
+        let amm_fee = amm_swap.as_ref().map_or(0, |(_, swap)| swap.fee);
+        if let Some((shares, swap)) = amm_swap {
+            apply_sell(&mut ctx.accounts.pool, market, side, shares, &swap);
+        }
+
+        let position = &mut ctx.accounts.position;
+        realize_proceeds(position, side, shares_sold, amount_out, amm_fee);
+        debit_position(position, side, shares_sold);
+
+        let event = trade_executed(
+            ctx.accounts.market.key(),
+            ctx.accounts.user.key(),
+            &ctx.accounts.pool,
+            reserves_before,
+            side,
+            OrderDirection::Sell,
+            amount_out,
+            shares_sold,
+            amm_fee,
+        )?;
+        record_price(
+            &ctx.accounts.market,
+            &mut ctx.accounts.price_history,
+            &event,
+        )?;
+        emit_cpi!(event);
+
+        msg!(
+            "Sold {} {:?} shares ({} to book, {} to AMM) for {} lamports",
+            shares_sold,
+            side,
+            shares_sold - amm_shares_in,
+            amm_shares_in,
+            amount_out
+        );
+        Ok(())
+    }
+
+    // ========================================
+    // Batch Auction Functions
+    // ========================================
+
+    /// Switch a market into batch-auction mode
+    ///
+    /// While enabled, AMM trades are queued with `submit_batch_order` and
+    /// cleared together at one uniform price by `clear_batch`, so execution
+    /// no longer depends on transaction ordering within a batch.
+    ///
+    /// # Arguments
+    /// * `interval` - Seconds each batch stays open for orders
+    pub fn enable_batch_auction(ctx: Context<EnableBatchAuction>, interval: i64) -> Result<()> {
+        require!(
+            ctx.accounts.market.status == MarketStatus::Active,
+            MarketError::MarketNotActive
+        );
+        require!(
+            ctx.accounts.market.authority == ctx.accounts.authority.key(),
+            MarketError::Unauthorized
+        );
+        require!(
+            (MIN_BATCH_INTERVAL..=MAX_BATCH_INTERVAL).contains(&interval),
+            MarketError::InvalidBatchInterval
+        );
+
+        let batch = &mut ctx.accounts.batch_auction;
+        if batch.market == Pubkey::default() {
+            batch.market = ctx.accounts.market.key();
+            batch.bump = ctx.bumps.batch_auction;
+        }
+        batch.interval = interval;
+        batch.batch_start = Clock::get()?.unix_timestamp;
+
+        ctx.accounts.market.batch_mode = true;
+
+        msg!("Batch auctions enabled with a {}s interval", interval);
+        Ok(())
+    }
+
+    /// Return a market to continuous trading once its batch queue is empty
+    pub fn disable_batch_auction(ctx: Context<DisableBatchAuction>) -> Result<()> {
+        require!(
+            ctx.accounts.market.authority == ctx.accounts.authority.key(),
+            MarketError::Unauthorized
+        );
+        require!(
+            ctx.accounts.batch_auction.orders.is_empty(),
+            MarketError::BatchNotEmpty
+        );
+
+        ctx.accounts.market.batch_mode = false;
+
+        msg!("Batch auctions disabled");
+        Ok(())
+    }
+
+    /// Queue a buy or sell for the current batch
+    ///
+    /// Buys escrow `amount` lamports in the queue, sells escrow `amount` shares
+    /// from the owner's position. Orders that cannot be filled at or above
+    /// `min_out` when the batch clears are returned.
+    ///
+    /// # Arguments
+    /// * `side` - Outcome to trade
+    /// * `direction` - Whether to buy or sell `side`
+    /// * `amount` - Lamports to spend (buy) or shares to sell (sell)
+    /// * `min_out` - Minimum shares (buy) or lamports (sell) to accept
+    pub fn submit_batch_order(
+        ctx: Context<SubmitBatchOrder>,
+        side: Outcome,
+        direction: OrderDirection,
+        amount: u64,
+        min_out: u64,
+    ) -> Result<()> {
+        require!(
+            ctx.accounts.market.status == MarketStatus::Active,
+            MarketError::MarketNotActive
+        );
+        require!(!ctx.accounts.config.paused, MarketError::ProtocolPaused);
+        require!(
+            ctx.accounts.market.batch_mode,
+            MarketError::BatchAuctionInactive
+        );
+        require!(amount > 0, MarketError::InvalidAmount);
+
+        let batch = &ctx.accounts.batch_auction;
+        require!(
+            Clock::get()?.unix_timestamp < batch.batch_start + batch.interval,
+            MarketError::BatchWindowClosed
+        );
+        require!(
+            batch.orders.len() < MAX_BATCH_ORDERS,
+            MarketError::BatchQueueFull
+        );
+
+        // Create the position up front so the clearing crank can credit it
+        let position = &mut ctx.accounts.position;
+        if position.user == Pubkey::default() {
+            position.user = ctx.accounts.owner.key();
+            position.market = ctx.accounts.market.key();
+            position.bump = ctx.bumps.position;
+        }
+        position.open_orders += 1;
+
+        match direction {
+            OrderDirection::Buy => {
+                let cpi_context = CpiContext::new(
+                    ctx.accounts.system_program.to_account_info(),
+                    anchor_lang::system_program::Transfer {
+                        from: ctx.accounts.owner.to_account_info(),
+                        to: ctx.accounts.batch_auction.to_account_info(),
+                    },
+                );
+                anchor_lang::system_program::transfer(cpi_context, amount)?;
+            }
+            OrderDirection::Sell => {
+                let shares_held = match side {
+                    Outcome::Yes => &mut position.yes_shares,
+                    Outcome::No => &mut position.no_shares,
+                };
+                require!(*shares_held >= amount, MarketError::InsufficientShares);
+                *shares_held -= amount;
+            }
+        }
+
+        let batch = &mut ctx.accounts.batch_auction;
+        let order_id = batch.next_order_id;
+        batch.next_order_id += 1;
+        batch.orders.push(BatchOrder {
+            order_id,
+            owner: ctx.accounts.owner.key(),
+            side,
+            direction,
+            amount,
+            min_out,
+        });
+
+        emit_cpi!(BatchOrderPlaced {
+            market: ctx.accounts.market.key(),
+            owner: ctx.accounts.owner.key(),
+            order_id,
+            batch_number: batch.batch_number,
+            side,
+            direction,
+            amount,
+            min_out,
+        });
+
+        msg!(
+            "Batch order {} queued: {:?} {} {:?} in batch {}",
+            order_id,
+            direction,
+            amount,
+            side,
+            batch.batch_number
+        );
+        Ok(())
+    }
+
+    /// Withdraw a queued order before its batch clears, returning its escrow
+    ///
+    /// Allowed in any market status, so escrow is never stranded by a halt or
+    /// an unexpected settlement.
+    pub fn cancel_batch_order(ctx: Context<CancelBatchOrder>, order_id: u64) -> Result<()> {
+        let batch = &mut ctx.accounts.batch_auction;
+        let index = batch
+            .orders
+            .iter()
+            .position(|order| order.order_id == order_id)
+            .ok_or(MarketError::OrderNotFound)?;
+        require_keys_eq!(
+            batch.orders[index].owner,
+            ctx.accounts.owner.key(),
+            MarketError::Unauthorized
+        );
+        let order = batch.orders.remove(index);
+
+        let position = &mut ctx.accounts.position;
+        match order.direction {
+            OrderDirection::Buy => {
+                ctx.accounts.batch_auction.sub_lamports(order.amount)?;
+                ctx.accounts.owner.add_lamports(order.amount)?;
+            }
+            OrderDirection::Sell => match order.side {
+                Outcome::Yes => position.yes_shares += order.amount,
+                Outcome::No => position.no_shares += order.amount,
+            },
+        }
+        position.open_orders = position.open_orders.saturating_sub(1);
+
+        msg!("Batch order {} cancelled", order_id);
+        Ok(())
+    }
+
+    /// Clear the current batch against the pool (permissionless crank)
+    ///
+    /// Every order trades at one uniform price: opposing flows cross against
+    /// each other and only their net imbalance is swapped along the curve (see
+    /// `price_batch`). Orders whose output falls short of `min_out` are returned
+    /// and the batch is re-priced without them.
+    ///
+    /// Every filled order emits a `TradeExecuted` carrying the reserves before
+    /// and after the whole batch.
+    ///
+    /// `remaining_accounts` must hold a `(position, owner)` pair per queued order, in order.
+    pub fn clear_batch<'info>(ctx: Context<'_, '_, 'info, 'info, ClearBatch<'info>>) -> Result<()> {
+        require!(
+            ctx.accounts.market.status == MarketStatus::Active,
+            MarketError::MarketNotActive
+        );
+        require!(!ctx.accounts.config.paused, MarketError::ProtocolPaused);
+
+        let now = Clock::get()?.unix_timestamp;
+        let batch = &ctx.accounts.batch_auction;
+        require!(
+            now >= batch.batch_start + batch.interval,
+            MarketError::BatchWindowOpen
+        );
+
+        let orders = batch.orders.clone();
+        require!(
+            ctx.remaining_accounts.len() == orders.len() * 2,
+            MarketError::InvalidBatchAccounts
+        );
+
+        require!(
+            ctx.accounts.pool.yes_reserve > 0 && ctx.accounts.pool.no_reserve > 0,
+            MarketError::PoolNotInitialized
+        );
+        let max_trade = ctx.accounts.pool.total_liquidity
+            * ctx.accounts.market.params.max_trade_size_bps
+            / BASIS_POINTS;
+
+        // Price the batch, dropping orders until every remaining one clears
+        let mut included = vec![true; orders.len()];
+        let (pool, market, outputs, fees) = loop {
+            let (pool, market, outputs, fees) =
+                price_batch(&ctx.accounts.pool, &ctx.accounts.market, &orders, &included);
+
+            // The net imbalance is a single swap and is held to the trade size cap
+            let grew_no = pool.no_reserve > ctx.accounts.pool.no_reserve;
+            let net_in = if grew_no {
+                pool.no_reserve - ctx.accounts.pool.no_reserve
+            } else {
+                pool.yes_reserve
+                    .saturating_sub(ctx.accounts.pool.yes_reserve)
+            };
+            if net_in > max_trade {
+                // Drop the largest order driving the imbalance and re-price
+                if let Some(i) = (0..orders.len())
+                    .filter(|&i| included[i] && flows_into_no(&orders[i]) == grew_no)
+                    .max_by_key(|&i| orders[i].amount)
+                {
+                    included[i] = false;
+                    continue;
+                }
+            }
+
+            let short: Vec<usize> = (0..orders.len())
+                .filter(|&i| included[i] && outputs[i] < orders[i].min_out.max(1))
+                .collect();
+            if short.is_empty() {
+                break (pool, market, outputs, fees);
+            }
+            for i in short {
+                included[i] = false;
+            }
+        };
+
+        // Check vault balance for sell proceeds
+        let sell_proceeds: u64 = (0..orders.len())
+            .filter(|&i| included[i] && orders[i].direction == OrderDirection::Sell)
+            .map(|i| outputs[i])
+            .sum();
+        let buy_collateral: u64 = (0..orders.len())
+            .filter(|&i| included[i] && orders[i].direction == OrderDirection::Buy)
+            .map(|i| orders[i].amount)
+            .sum();
+        require!(
+            ctx.accounts.vault.lamports() + buy_collateral >= sell_proceeds,
+            MarketError::InsufficientVaultFunds
+        );
+
+        if buy_collateral > 0 {
+            ctx.accounts.batch_auction.sub_lamports(buy_collateral)?;
+            ctx.accounts.vault.add_lamports(buy_collateral)?;
+        }
+
+        let market_key = ctx.accounts.market.key();
+
+        for (i, (order, accounts)) in orders
+            .iter()
+            .zip(ctx.remaining_accounts.chunks(2))
+            .enumerate()
+        {
+            let position_info = &accounts[0];
+            let owner_info = &accounts[1];
+
+            require_keys_eq!(
+                owner_info.key(),
+                order.owner,
+                MarketError::InvalidBatchAccounts
+            );
+            let (expected_position, _) = Pubkey::find_program_address(
+                &[POSITION_SEED, market_key.as_ref(), order.owner.as_ref()],
+                &crate::ID,
+            );
+            require_keys_eq!(
+                position_info.key(),
+                expected_position,
+                MarketError::InvalidBatchAccounts
+            );
+
+            let mut position: Account<'info, Position> = Account::try_from(position_info)?;
+            position.open_orders = position.open_orders.saturating_sub(1);
+            match (order.direction, included[i]) {
+                (OrderDirection::Buy, true) => {
+                    credit_position(&mut position, order.side, outputs[i], order.amount);
+                    position.fees_paid += fees[i];
+                }
+                (OrderDirection::Sell, true) => {
+                    realize_proceeds(&mut position, order.side, order.amount, outputs[i], fees[i]);
+                    transfer_from_vault(
+                        &ctx.accounts.vault,
+                        owner_info,
+                        &ctx.accounts.system_program.to_account_info(),
+                        &market_key,
+                        ctx.bumps.vault,
+                        outputs[i],
+                    )?;
+                }
+                (OrderDirection::Buy, false) => {
+                    ctx.accounts.batch_auction.sub_lamports(order.amount)?;
+                    owner_info.add_lamports(order.amount)?;
+                }
+                (OrderDirection::Sell, false) => match order.side {
+                    Outcome::Yes => position.yes_shares += order.amount,
+                    Outcome::No => position.no_shares += order.amount,
+                },
+            }
+            position.exit(&crate::ID)?;
+        }
+
+        let reserves_before = (ctx.accounts.pool.yes_reserve, ctx.accounts.pool.no_reserve);
+        ctx.accounts.pool.set_inner(pool);
+        ctx.accounts.market.set_inner(market);
+
+        for (i, order) in orders.iter().enumerate().filter(|&(i, _)| included[i]) {
+            let (amount, shares) = match order.direction {
+                OrderDirection::Buy => (order.amount, outputs[i]),
+                OrderDirection::Sell => (outputs[i], order.amount),
+            };
+            let event = trade_executed(
+                market_key,
+                order.owner,
+                &ctx.accounts.pool,
+                reserves_before,
+                order.side,
+                order.direction,
+                amount,
+                shares,
+                fees[i],
+            )?;
+            record_price(
+                &ctx.accounts.market,
+                &mut ctx.accounts.price_history,
+                &event,
+            )?;
+            emit_cpi!(event);
+        }
+
+        let batch = &mut ctx.accounts.batch_auction;
+        let cleared = included.iter().filter(|&&inc| inc).count();
+        msg!(
+            "Batch {} cleared: {} of {} orders filled",
+            batch.batch_number,
+            cleared,
+            orders.len()
+        );
+
+        batch.orders.clear();
+        batch.batch_number += 1;
+        batch.batch_start = now;
+        Ok(())
+    }
+
+    // ========================================
+    // RFQ Functions
+    // ========================================
+
+    /// Allow a market maker to fill RFQ trades in this market
+    pub fn approve_rfq_maker(ctx: Context<ApproveRfqMaker>) -> Result<()> {
+        require!(
+            ctx.accounts.market.authority == ctx.accounts.authority.key(),
+            MarketError::Unauthorized
+        );
+
+        let rfq_maker = &mut ctx.accounts.rfq_maker;
+        rfq_maker.market = ctx.accounts.market.key();
+        rfq_maker.maker = ctx.accounts.maker.key();
+        rfq_maker.approved_at = Clock::get()?.unix_timestamp;
+        rfq_maker.bump = ctx.bumps.rfq_maker;
+
+        msg!("RFQ maker {} approved", rfq_maker.maker);
+        Ok(())
+    }
+
+    /// Revoke a market maker's RFQ approval
+    pub fn revoke_rfq_maker(ctx: Context<RevokeRfqMaker>) -> Result<()> {
+        require!(
+            ctx.accounts.market.authority == ctx.accounts.authority.key(),
+            MarketError::Unauthorized
+        );
+
+        msg!("RFQ maker {} revoked", ctx.accounts.rfq_maker.maker);
+        Ok(())
+    }
+
+    /// Settle a fixed-price trade quoted off-chain by an approved maker
+    ///
+    /// Taker and maker co-sign. Together they mint complete sets in the vault:
+    /// the taker pays `price` per share for `side`, the maker pays the
+    /// complement for the opposite side. The pool is not touched, so the trade
+    /// is not bound by the AMM's maximum trade size. The taker's side is
+    /// reported as a `TradeExecuted` at the pool's unchanged reserves.
+    ///
+    /// The maker's signature covers every argument, `quote_expiry` included, so
+    /// the taker cannot fill the quote on other terms or after it goes stale.
+    pub fn rfq_trade(
+        ctx: Context<RfqTrade>,
+        side: Outcome,
+        shares: u64,
+        price: u64,
+        quote_expiry: i64,
+    ) -> Result<()> {
+        require!(
+            ctx.accounts.market.status == MarketStatus::Active,
+            MarketError::MarketNotActive
+        );
+        require!(!ctx.accounts.config.paused, MarketError::ProtocolPaused);
+        require!(
+            Clock::get()?.unix_timestamp <= quote_expiry,
+            MarketError::QuoteExpired
+        );
+        require_keys_neq!(
+            ctx.accounts.taker.key(),
+            ctx.accounts.maker.key(),
+            MarketError::Unauthorized
+        );
+        require!(shares >= MIN_SHARES_OUTPUT, MarketError::OutputTooSmall);
+        require!(
+            price > 0 && price < PRICE_DECIMALS,
+            MarketError::InvalidLimitPrice
+        );
+
+        let taker_cost = (shares as u128 * price as u128).div_ceil(PRICE_DECIMALS as u128) as u64;
+        let maker_cost = shares - taker_cost;
+
+        for (payer, amount) in [
+            (ctx.accounts.taker.to_account_info(), taker_cost),
+            (ctx.accounts.maker.to_account_info(), maker_cost),
+        ] {
+            if amount == 0 {
+                continue;
+            }
+            let cpi_context = CpiContext::new(
+                ctx.accounts.system_program.to_account_info(),
+                anchor_lang::system_program::Transfer {
+                    from: payer,
+                    to: ctx.accounts.vault.to_account_info(),
+                },
+            );
+            anchor_lang::system_program::transfer(cpi_context, amount)?;
+        }
+
+        let market = &mut ctx.accounts.market;
+        market.total_yes_shares += shares;
+        market.total_no_shares += shares;
+        market.trading_started = true;
+
+        let opposite = match side {
+            Outcome::Yes => Outcome::No,
+            Outcome::No => Outcome::Yes,
+        };
+        for (position, user, bump, position_side, position_price) in [
+            (
+                &mut ctx.accounts.taker_position,
+                ctx.accounts.taker.key(),
+                ctx.bumps.taker_position,
+                side,
+                price,
+            ),
+            (
+                &mut ctx.accounts.maker_position,
+                ctx.accounts.maker.key(),
+                ctx.bumps.maker_position,
+                opposite,
+                PRICE_DECIMALS - price,
+            ),
+        ] {
+            if position.user == Pubkey::default() {
+                position.user = user;
+                position.market = market.key();
+                position.bump = bump;
+            }
+            credit_position(
+                position,
+                position_side,
+                shares,
+                shares_cost(shares, position_price),
+            );
+        }
+
+        // The pool is untouched, so its reserves are reported unchanged
+        let reserves = (ctx.accounts.pool.yes_reserve, ctx.accounts.pool.no_reserve);
+        emit_cpi!(trade_executed(
+            ctx.accounts.market.key(),
+            ctx.accounts.taker.key(),
+            &ctx.accounts.pool,
+            reserves,
+            side,
+            OrderDirection::Buy,
+            taker_cost,
+            shares,
+            0,
+        )?);
+
+        msg!(
+            "RFQ trade: taker bought {} {:?} shares at {} from maker {}",
+            shares,
+            side,
+            price,
+            ctx.accounts.maker.key()
+        );
+        Ok(())
+    }
+
+    /// Resolve the market using Pyth oracle price feed
+    ///
+    /// Conditional markets must pass their parent market. If the parent settled
+    /// against the condition the market is invalidated instead of resolved.
+    pub fn resolve_market(ctx: Context<ResolveMarket>) -> Result<()> {
+        let market = &ctx.accounts.market;
+
+        require!(
+            market.status == MarketStatus::Active,
+            MarketError::MarketNotActive
+        );
+        check_batch_queue_empty(market, &ctx.accounts.batch_auction)?;
+
+        if let Some(parent_key) = market.parent_market {
+            let parent = ctx
+                .accounts
+                .parent_market
+                .as_ref()
+                .ok_or(MarketError::InvalidParentMarket)?;
+            require_keys_eq!(parent.key(), parent_key, MarketError::InvalidParentMarket);
+
+            let condition_met = match parent.status {
+                MarketStatus::Resolved => parent.outcome == market.parent_outcome,
+                MarketStatus::Cancelled | MarketStatus::Invalid => false,
+                MarketStatus::Active | MarketStatus::Halted => {
+                    return err!(MarketError::ParentNotResolved)
+                }
+            };
+
+            if !condition_met {
+                release_parent(&ctx.accounts.market, &mut ctx.accounts.parent_market)?;
+                let market = &mut ctx.accounts.market;
+                market.status = MarketStatus::Invalid;
+                market.resolution_timestamp = Some(Clock::get()?.unix_timestamp);
+
+                emit_cpi!(MarketResolved {
+                    market: market.key(),
+                    status: market.status,
+                    outcome: None,
+                    resolution_price: None,
+                });
+
+                msg!("Market invalidated: parent market did not meet condition");
+                return Ok(());
+            }
+        }
+
+        require!(
+            Clock::get()?.unix_timestamp >= market.expiration + market.params.resolution_delay,
+            MarketError::MarketNotExpired
+        );
+        require!(
+            ctx.accounts.oracle_feed.can_resolve,
+            MarketError::OracleFeedNotPermitted
+        );
+
+        // Read price from Pyth oracle
+        let price_account_info = &ctx.accounts.pyth_price_account;
+        let price_feed = SolanaPriceAccount::account_info_to_feed(price_account_info)
+            .map_err(|_| MarketError::InvalidOraclePrice)?;
+
+        let current_price = price_feed
+            .get_price_no_older_than(
+                Clock::get()?.unix_timestamp,
+                market.params.max_oracle_staleness,
+            )
+            .ok_or(MarketError::InvalidOraclePrice)?;
+
+        // Check confidence interval
+        require!(
+            current_price.conf <= market.max_confidence,
+            MarketError::ConfidenceTooHigh
+        );
+
+        // Determine outcome
+        let outcome = if current_price.price >= market.strike_price {
+            Outcome::Yes
+        } else {
+            Outcome::No
+        };
+
+        // Update market state
+        release_parent(&ctx.accounts.market, &mut ctx.accounts.parent_market)?;
+        let market = &mut ctx.accounts.market;
+        market.status = MarketStatus::Resolved;
+        market.outcome = Some(outcome);
+        market.resolution_price = Some(current_price.price);
+        market.resolution_timestamp = Some(Clock::get()?.unix_timestamp);
+
+        emit_cpi!(MarketResolved {
+            market: market.key(),
+            status: market.status,
+            outcome: market.outcome,
+            resolution_price: market.resolution_price,
+        });
+
+        msg!(
+            "Market resolved: {:?} (price: {}, strike: {})",
+            outcome,
+            current_price.price,
+            market.strike_price
+        );
+        Ok(())
+    }
+
+    /// Resolve a market by hand when the oracle has failed to resolve it
+    ///
+    /// Only the market's resolver may call this, and only once the normal
+    /// resolution window has been open for `MANUAL_RESOLUTION_GRACE` seconds.
+    pub fn resolve_market_manual(
+        ctx: Context<ResolveMarketManual>,
+        outcome: Outcome,
+    ) -> Result<()> {
+        let market = &ctx.accounts.market;
+        require!(
+            market.status == MarketStatus::Active,
+            MarketError::MarketNotActive
+        );
+        require!(
+            market.resolver == ctx.accounts.resolver.key(),
+            MarketError::Unauthorized
+        );
+        check_batch_queue_empty(market, &ctx.accounts.batch_auction)?;
+        require!(
+            Clock::get()?.unix_timestamp
+                >= market.expiration + market.params.resolution_delay + MANUAL_RESOLUTION_GRACE,
+            MarketError::ManualResolutionTooEarly
+        );
+
+        // A conditional market may only be resolved by hand once its condition holds;
+        // otherwise `resolve_market` invalidates it
+        if let Some(parent_key) = market.parent_market {
+            let parent = ctx
+                .accounts
+                .parent_market
+                .as_ref()
+                .ok_or(MarketError::InvalidParentMarket)?;
+            require_keys_eq!(parent.key(), parent_key, MarketError::InvalidParentMarket);
+            require!(
+                parent.status == MarketStatus::Resolved && parent.outcome == market.parent_outcome,
+                MarketError::ParentNotResolved
+            );
+        }
+
+        release_parent(&ctx.accounts.market, &mut ctx.accounts.parent_market)?;
+        let market = &mut ctx.accounts.market;
+        market.status = MarketStatus::Resolved;
+        market.outcome = Some(outcome);
+        market.resolution_price = None;
+        market.resolution_timestamp = Some(Clock::get()?.unix_timestamp);
+
+        emit_cpi!(MarketResolved {
+            market: market.key(),
+            status: market.status,
+            outcome: market.outcome,
+            resolution_price: None,
+        });
+
+        msg!("Market manually resolved: {:?}", outcome);
+        Ok(())
+    }
+
+    /// Claim winnings after market resolution
+    pub fn claim_winnings(ctx: Context<ClaimWinnings>) -> Result<()> {
+        let market = &ctx.accounts.market;
+        let position = &ctx.accounts.position;
+
+        require!(
+            market.status == MarketStatus::Resolved,
+            MarketError::MarketNotResolved
+        );
+        require!(
+            position.user == ctx.accounts.user.key(),
+            MarketError::InvalidPosition
+        );
+
+        // Calculate remaining winnings based on outcome
+        let winning_shares = unclaimed_winnings(market, position);
+        require!(
+            winning_shares > 0 || position.claimed_shares == 0,
+            MarketError::AlreadyClaimed
+        );
+        require!(winning_shares > 0, MarketError::NoWinnings);
+
+        // Each winning share is worth 1 unit of collateral (1 lamport per share unit)
+        let payout = winning_shares;
+
+        // Check vault balance
+        let vault_lamports = ctx.accounts.vault.lamports();
+        require!(
+            vault_lamports >= payout,
+            MarketError::InsufficientVaultFunds
+        );
+
+        // Transfer winnings from vault
+        transfer_from_vault(
+            &ctx.accounts.vault,
+            &ctx.accounts.user.to_account_info(),
+            &ctx.accounts.system_program.to_account_info(),
+            &ctx.accounts.market.key(),
+            ctx.bumps.vault,
+            payout,
+        )?;
+
+        // Mark shares as claimed
+        let outcome = ctx
+            .accounts
+            .market
+            .outcome
+            .ok_or(MarketError::MarketNotResolved)?;
+        let position = &mut ctx.accounts.position;
+        realize_proceeds(position, outcome, winning_shares, payout, 0);
+        position.claimed_shares += winning_shares;
+        ctx.accounts.market.total_claimed += winning_shares;
+
+        emit_cpi!(Claimed {
+            market: ctx.accounts.market.key(),
+            user: ctx.accounts.user.key(),
+            recipient: ctx.accounts.user.key(),
+            shares: winning_shares,
+            payout,
+            fee: 0,
+        });
+
+        msg!(
+            "Claimed {} lamports for {} winning shares",
+            payout,
+            winning_shares
+        );
+        Ok(())
+    }
+
+    /// Claim part of a position's winnings to another account
+    ///
+    /// Lets custodial setups and smart wallets sweep to a treasury and claim in tranches.
+    ///
+    /// # Arguments
+    /// * `amount` - Winning shares to redeem, each paying 1 lamport
+    pub fn claim_winnings_to(ctx: Context<ClaimWinningsTo>, amount: u64) -> Result<()> {
+        let market = &ctx.accounts.market;
+        let position = &ctx.accounts.position;
+
+        require!(
+            market.status == MarketStatus::Resolved,
+            MarketError::MarketNotResolved
+        );
+        require!(amount > 0, MarketError::InvalidAmount);
+
+        let unclaimed = unclaimed_winnings(market, position);
+        require!(unclaimed > 0, MarketError::NoWinnings);
+        require!(amount <= unclaimed, MarketError::InsufficientShares);
+
+        // Check vault balance
+        let vault_lamports = ctx.accounts.vault.lamports();
+        require!(
+            vault_lamports >= amount,
+            MarketError::InsufficientVaultFunds
+        );
+
+        // Transfer winnings from vault to the recipient
+        transfer_from_vault(
+            &ctx.accounts.vault,
+            &ctx.accounts.recipient,
+            &ctx.accounts.system_program.to_account_info(),
+            &ctx.accounts.market.key(),
+            ctx.bumps.vault,
+            amount,
+        )?;
+
+        let outcome = ctx
+            .accounts
+            .market
+            .outcome
+            .ok_or(MarketError::MarketNotResolved)?;
+        let position = &mut ctx.accounts.position;
+        realize_proceeds(position, outcome, amount, amount, 0);
+        position.claimed_shares += amount;
+        ctx.accounts.market.total_claimed += amount;
+
+        emit_cpi!(Claimed {
+            market: ctx.accounts.market.key(),
+            user: position.user,
+            recipient: ctx.accounts.recipient.key(),
+            shares: amount,
+            payout: amount,
+            fee: 0,
+        });
+
+        msg!(
+            "Claimed {} of {} winning shares to {}",
+            amount,
+            unclaimed,
+            ctx.accounts.recipient.key()
+        );
+        Ok(())
+    }
+
+    /// Claim winnings from several resolved markets at once
+    ///
+    /// Returns a summary per market, in order. Markets that are unresolved,
+    /// already claimed or without winnings are skipped and report zero. Each
+    /// market's vault pays its own share directly to the caller.
+    ///
+    /// `remaining_accounts` must hold up to `MAX_CLAIM_MARKETS` `(market, vault, position)` triples.
+    pub fn claim_many<'info>(
+        ctx: Context<'_, '_, 'info, 'info, ClaimMany<'info>>,
+    ) -> Result<Vec<ClaimSummary>> {
+        require!(
+            !ctx.remaining_accounts.is_empty() && ctx.remaining_accounts.len() % 3 == 0,
+            MarketError::InvalidClaimAccounts
+        );
+        let markets = ctx.remaining_accounts.len() / 3;
+        require!(markets <= MAX_CLAIM_MARKETS, MarketError::TooManyClaims);
+
+        let user_key = ctx.accounts.user.key();
+        let mut summaries: Vec<ClaimSummary> = Vec::with_capacity(markets);
+
+        for accounts in ctx.remaining_accounts.chunks(3) {
+            let market_info = &accounts[0];
+            let vault_info = &accounts[1];
+            let position_info = &accounts[2];
+
+            let mut market: Account<'info, Market> = Account::try_from(market_info)?;
+            let market_key = market.key();
+
+            let (expected_vault, vault_bump) =
+                Pubkey::find_program_address(&[VAULT_SEED, market_key.as_ref()], &crate::ID);
+            require_keys_eq!(
+                vault_info.key(),
+                expected_vault,
+                MarketError::InvalidClaimAccounts
+            );
+            let (expected_position, _) = Pubkey::find_program_address(
+                &[POSITION_SEED, market_key.as_ref(), user_key.as_ref()],
+                &crate::ID,
+            );
+            require_keys_eq!(
+                position_info.key(),
+                expected_position,
+                MarketError::InvalidClaimAccounts
+            );
+
+            let mut position: Account<'info, Position> = Account::try_from(position_info)?;
+            let winning_shares = if market.status == MarketStatus::Resolved {
+                unclaimed_winnings(&market, &position)
+            } else {
+                0
+            };
+            summaries.push(ClaimSummary {
+                market: market_key,
+                shares: winning_shares,
+                payout: winning_shares,
+            });
+
+            if let (Some(outcome), true) = (market.outcome, winning_shares > 0) {
+                transfer_from_vault(
+                    vault_info,
+                    &ctx.accounts.user.to_account_info(),
+                    &ctx.accounts.system_program.to_account_info(),
+                    &market_key,
+                    vault_bump,
+                    winning_shares,
+                )?;
+
+                realize_proceeds(&mut position, outcome, winning_shares, winning_shares, 0);
+                position.claimed_shares += winning_shares;
+                market.total_claimed += winning_shares;
+                position.exit(&crate::ID)?;
+                market.exit(&crate::ID)?;
+
+                emit_cpi!(Claimed {
+                    market: market_key,
+                    user: user_key,
+                    recipient: user_key,
+                    shares: winning_shares,
+                    payout: winning_shares,
+                    fee: 0,
+                });
+            }
+        }
+
+        msg!(
+            "Claimed {} lamports from {} of {} markets",
+            summaries.iter().map(|summary| summary.payout).sum::<u64>(),
+            summaries
+                .iter()
+                .filter(|summary| summary.payout > 0)
+                .count(),
+            markets
+        );
+        Ok(summaries)
+    }
+
+    /// Pay out winning positions on a resolved market to their owners
+    ///
+    /// Permissionless. Each paid claim is charged `CRANK_FEE`, capped at
+    /// `MAX_CRANK_FEE_BPS` of the payout so small positions keep their winnings,
+    /// which goes to the cranker. Positions already claimed or without
+    /// winnings are skipped, but are still closed when `close_positions` is set.
+    /// Positions with open orders or parlay legs are never closed.
+    ///
+    /// `remaining_accounts` must hold `(position, owner)` pairs.
+    pub fn crank_claims<'info>(
+        ctx: Context<'_, '_, 'info, 'info, CrankClaims<'info>>,
+        close_positions: bool,
+    ) -> Result<()> {
+        let market = &ctx.accounts.market;
+        require!(
+            market.status == MarketStatus::Resolved,
+            MarketError::MarketNotResolved
+        );
+        require!(
+            !ctx.remaining_accounts.is_empty() && ctx.remaining_accounts.len() % 2 == 0,
+            MarketError::InvalidClaimAccounts
+        );
+
+        let market_key = market.key();
+        let mut claims = 0u64;
+        let mut total_fee = 0u64;
+        let mut total_claimed = 0u64;
+
+        for accounts in ctx.remaining_accounts.chunks(2) {
+            let position_info = &accounts[0];
+            let owner_info = &accounts[1];
+
+            let mut position: Account<'info, Position> = Account::try_from(position_info)?;
+            let expected_position = Pubkey::create_program_address(
+                &[
+                    POSITION_SEED,
+                    market_key.as_ref(),
+                    owner_info.key.as_ref(),
+                    &[position.bump],
+                ],
+                &crate::ID,
+            )
+            .map_err(|_| MarketError::InvalidClaimAccounts)?;
+            require_keys_eq!(
+                position_info.key(),
+                expected_position,
+                MarketError::InvalidClaimAccounts
+            );
+
+            let winning_shares = unclaimed_winnings(market, &position);
+
+            if winning_shares > 0 {
+                let fee = CRANK_FEE.min(
+                    (winning_shares as u128 * MAX_CRANK_FEE_BPS as u128 / BASIS_POINTS as u128)
+                        as u64,
+                );
+                transfer_from_vault(
+                    &ctx.accounts.vault,
+                    owner_info,
+                    &ctx.accounts.system_program.to_account_info(),
+                    &market_key,
+                    ctx.bumps.vault,
+                    winning_shares - fee,
+                )?;
+
+                if let Some(outcome) = market.outcome {
+                    realize_proceeds(
+                        &mut position,
+                        outcome,
+                        winning_shares,
+                        winning_shares - fee,
+                        fee,
+                    );
+                }
+                position.claimed_shares += winning_shares;
+                claims += 1;
+                total_fee += fee;
+                total_claimed += winning_shares;
+
+                emit_cpi!(Claimed {
+                    market: market_key,
+                    user: position.user,
+                    recipient: owner_info.key(),
+                    shares: winning_shares,
+                    payout: winning_shares - fee,
+                    fee,
+                });
+            }
+
+            if close_positions && position.open_orders == 0 {
+                position.close(owner_info.clone())?;
+            } else {
+                position.exit(&crate::ID)?;
+            }
+        }
+
+        if total_fee > 0 {
+            transfer_from_vault(
+                &ctx.accounts.vault,
+                &ctx.accounts.cranker.to_account_info(),
+                &ctx.accounts.system_program.to_account_info(),
+                &market_key,
+                ctx.bumps.vault,
+                total_fee,
+            )?;
+        }
+        ctx.accounts.market.total_claimed += total_claimed;
+
+        msg!(
+            "Cranked {} claims for {} winning shares, {} lamports in fees",
+            claims,
+            total_claimed,
+            total_fee
+        );
+        Ok(())
+    }
+
+    /// Refund a position in a cancelled or invalidated market
+    ///
+    /// Shares on both sides are refunded at their average entry price.
+    pub fn claim_refund(ctx: Context<ClaimWinnings>) -> Result<()> {
+        let market = &ctx.accounts.market;
+        let position = &ctx.accounts.position;
+
+        require!(
+            market.status == MarketStatus::Cancelled || market.status == MarketStatus::Invalid,
+            MarketError::MarketNotRefundable
+        );
+        require!(
+            position.user == ctx.accounts.user.key(),
+            MarketError::InvalidPosition
+        );
+        require!(position.claimed_shares == 0, MarketError::AlreadyClaimed);
+
+        let yes_refund = shares_cost(position.yes_shares, position.yes_avg_price);
+        let no_refund = shares_cost(position.no_shares, position.no_avg_price);
+        let refund = yes_refund + no_refund;
+
+        require!(refund > 0, MarketError::NoRefund);
+
+        // Check vault balance
+        let vault_lamports = ctx.accounts.vault.lamports();
+        require!(
+            vault_lamports >= refund,
+            MarketError::InsufficientVaultFunds
+        );
+
+        // Transfer refund from vault
+        transfer_from_vault(
+            &ctx.accounts.vault,
+            &ctx.accounts.user.to_account_info(),
+            &ctx.accounts.system_program.to_account_info(),
+            &ctx.accounts.market.key(),
+            ctx.bumps.vault,
+            refund,
+        )?;
+
+        // Refunds settle every share at once
+        let position = &mut ctx.accounts.position;
+        let (yes_shares, no_shares) = (position.yes_shares, position.no_shares);
+        realize_proceeds(position, Outcome::Yes, yes_shares, yes_refund, 0);
+        realize_proceeds(position, Outcome::No, no_shares, no_refund, 0);
+        position.claimed_shares = yes_shares + no_shares;
+
+        emit_cpi!(Claimed {
+            market: ctx.accounts.market.key(),
+            user: ctx.accounts.user.key(),
+            recipient: ctx.accounts.user.key(),
+            shares: yes_shares + no_shares,
+            payout: refund,
+            fee: 0,
+        });
+
+        msg!("Refunded {} lamports", refund);
+        Ok(())
+    }
+
+    /// Close a settled position, returning its rent to the owner
+    ///
+    /// Positions with open limit, book or batch orders or parlay legs cannot be
+    /// closed. Otherwise empty positions can be closed at any time; the rest need
+    /// a settled market and the position claimed or holding nothing of value.
+    /// Positions in a closed market can always be closed.
+    pub fn close_position(ctx: Context<ClosePosition>) -> Result<()> {
+        let position = &ctx.accounts.position;
+        let market_info = &ctx.accounts.market;
+        require!(position.open_orders == 0, MarketError::OrdersOutstanding);
+
+        if !market_info.data_is_empty() && (position.yes_shares > 0 || position.no_shares > 0) {
+            require_keys_eq!(*market_info.owner, crate::ID, MarketError::InvalidPosition);
+            let market = Market::try_deserialize(&mut &market_info.try_borrow_data()?[..])?;
+            let settled = match market.status {
+                MarketStatus::Resolved => unclaimed_winnings(&market, position) == 0,
+                MarketStatus::Cancelled | MarketStatus::Invalid => position.claimed_shares > 0,
+                MarketStatus::Active | MarketStatus::Halted => false,
+            };
+            require!(settled, MarketError::PositionNotSettled);
+        }
+
+        msg!("Position closed");
+        Ok(())
+    }
+
+    /// Close an empty LP position, returning its rent to the owner
+    ///
+    /// LP tokens must be fully withdrawn and fees claimed first, unless the pool is closed.
+    pub fn close_lp_position(ctx: Context<CloseLpPosition>) -> Result<()> {
+        let pool_info = &ctx.accounts.pool;
+
+        if !pool_info.data_is_empty() {
+            require_keys_eq!(*pool_info.owner, crate::ID, MarketError::InvalidPosition);
+            let mut pool = Pool::try_deserialize(&mut &pool_info.try_borrow_data()?[..])?;
+            let lp_position = &mut ctx.accounts.lp_position;
+            settle_lp_fees(&pool, lp_position);
+            require!(
+                lp_position.lp_tokens == 0 && lp_position.fees_owed == 0,
+                MarketError::PositionNotSettled
+            );
+
+            pool.lp_position_count = pool.lp_position_count.saturating_sub(1);
+            pool.try_serialize(&mut &mut pool_info.try_borrow_mut_data()?[..])?;
+        }
+
+        msg!("LP position closed");
+        Ok(())
+    }
+
+    /// Build a parlay by locking shares from the caller's positions
+    ///
+    /// Each leg settles on its own: winning legs pay their shares and legs in
+    /// markets that are cancelled or invalidated are refunded at entry price.
+    /// Locking realizes the shares' cost basis against each position; the
+    /// payout is realized when the parlay settles.
+    ///
+    /// # Arguments
+    /// * `parlay_id` - Caller-chosen identifier, unique per owner
+    /// * `legs` - Side and share count to lock for each leg
+    ///
+    /// `remaining_accounts` must hold a `(market, position)` pair per leg, in order.
+    pub fn create_parlay<'info>(
+        ctx: Context<'_, '_, 'info, 'info, CreateParlay<'info>>,
+        parlay_id: u64,
+        legs: Vec<ParlayLegArgs>,
+    ) -> Result<()> {
+        require!(!ctx.accounts.config.paused, MarketError::ProtocolPaused);
+        require!(
+            legs.len() >= MIN_PARLAY_LEGS && legs.len() <= MAX_PARLAY_LEGS,
+            MarketError::InvalidParlayLegs
+        );
+        require!(
+            ctx.remaining_accounts.len() == legs.len() * 2,
+            MarketError::InvalidParlayLegs
+        );
+
+        let owner = ctx.accounts.owner.key();
+        let mut parlay_legs: Vec<ParlayLeg> = Vec::with_capacity(legs.len());
+
+        for (leg, accounts) in legs.iter().zip(ctx.remaining_accounts.chunks(2)) {
+            require!(leg.shares > 0, MarketError::InvalidAmount);
+
+            let market_info = &accounts[0];
+            let position_info = &accounts[1];
+
+            let market: Account<'info, Market> = Account::try_from(market_info)?;
+            require!(
+                market.status == MarketStatus::Active,
+                MarketError::MarketNotActive
+            );
+            require!(
+                !parlay_legs.iter().any(|l| l.market == market.key()),
+                MarketError::DuplicateParlayMarket
+            );
+
+            let (expected_position, _) = Pubkey::find_program_address(
+                &[POSITION_SEED, market.key().as_ref(), owner.as_ref()],
+                &crate::ID,
+            );
+            require_keys_eq!(
+                position_info.key(),
+                expected_position,
+                MarketError::InvalidPosition
+            );
+            require!(position_info.is_writable, MarketError::InvalidPosition);
+
+            // Lock the shares by moving them out of the position
+            let mut position: Account<'info, Position> = Account::try_from(position_info)?;
+            let entry_price = match leg.side {
+                Outcome::Yes => {
+                    require!(
+                        position.yes_shares >= leg.shares,
+                        MarketError::InsufficientShares
+                    );
+                    position.yes_shares -= leg.shares;
+                    position.yes_avg_price
+                }
+                Outcome::No => {
+                    require!(
+                        position.no_shares >= leg.shares,
+                        MarketError::InsufficientShares
+                    );
+                    position.no_shares -= leg.shares;
+                    position.no_avg_price
+                }
+            };
+            // The locked shares leave the position; their payout is booked on settlement
+            realize_proceeds(&mut position, leg.side, leg.shares, 0, 0);
+            position.open_orders += 1;
+            position.exit(&crate::ID)?;
+
+            parlay_legs.push(ParlayLeg {
+                market: market.key(),
+                side: leg.side,
+                shares: leg.shares,
+                entry_price,
+            });
+        }
+
+        let parlay = &mut ctx.accounts.parlay;
+        parlay.owner = owner;
+        parlay.parlay_id = parlay_id;
+        parlay.legs = parlay_legs;
+        parlay.created_at = Clock::get()?.unix_timestamp;
+        parlay.bump = ctx.bumps.parlay;
+
+        emit_cpi!(ParlayCreated {
+            parlay: parlay.key(),
+            owner,
+            parlay_id,
+            legs: parlay.legs.clone(),
+        });
+
+        msg!(
+            "Parlay {} created with {} legs",
+            parlay_id,
+            parlay.legs.len()
+        );
+        Ok(())
+    }
+
+    /// Settle a parlay once every leg's market has finished
+    ///
+    /// The parlay pays only if every non-voided leg won: the owner then receives
+    /// each winning leg's shares plus voided legs refunded at entry price. If any
+    /// leg lost the owner receives nothing and the locked collateral backing the
+    /// winning and voided legs is swept to the treasury, so nothing is stranded
+    /// in the legs' vaults. Legs whose market has since been closed count as
+    /// voided with nothing left to refund. The parlay account is closed.
+    ///
+    /// `remaining_accounts` must hold a `(market, vault, position)` triple per leg, in order.
+    pub fn settle_parlay<'info>(
+        ctx: Context<'_, '_, 'info, 'info, SettleParlay<'info>>,
+    ) -> Result<()> {
+        let parlay = &ctx.accounts.parlay;
+        require!(
+            ctx.remaining_accounts.len() == parlay.legs.len() * 3,
+            MarketError::InvalidParlayLegs
+        );
+
+        // Work out each leg's payout before moving any funds
+        let mut markets: Vec<Option<Account<'info, Market>>> =
+            Vec::with_capacity(parlay.legs.len());
+        let mut collateral: Vec<u64> = Vec::with_capacity(parlay.legs.len());
+        let mut all_won = true;
+        for (leg, accounts) in parlay.legs.iter().zip(ctx.remaining_accounts.chunks(3)) {
+            require_keys_eq!(
+                accounts[0].key(),
+                leg.market,
+                MarketError::InvalidParlayLegs
+            );
+            // A closed market's vault has been swept, leaving nothing to pay for its leg
+            if accounts[0].data_is_empty() {
+                markets.push(None);
+                collateral.push(0);
+                continue;
+            }
+            let market: Account<'info, Market> = Account::try_from(&accounts[0])?;
+
+            // Voided legs neither win nor lose the parlay
+            let amount = match market.status {
+                MarketStatus::Resolved => {
+                    if market.outcome == Some(leg.side) {
+                        leg.shares
+                    } else {
+                        all_won = false;
+                        0
+                    }
+                }
+                MarketStatus::Cancelled | MarketStatus::Invalid => {
+                    shares_cost(leg.shares, leg.entry_price)
+                }
+                MarketStatus::Active | MarketStatus::Halted => {
+                    return err!(MarketError::MarketNotResolved)
+                }
+            };
+            markets.push(Some(market));
+            collateral.push(amount);
+        }
+
+        // A losing parlay forfeits its collateral to the treasury
+        let recipient = if all_won {
+            ctx.accounts.owner.to_account_info()
+        } else {
+            ctx.accounts.treasury.to_account_info()
+        };
+
+        let mut total_payout = 0u64;
+        for (((leg, accounts), market), amount) in parlay
+            .legs
+            .iter()
+            .zip(ctx.remaining_accounts.chunks(3))
+            .zip(markets)
+            .zip(collateral)
+        {
+            // Release the leg's hold on the position it was locked from
+            let position_info = &accounts[2];
+            let (expected_position, _) = Pubkey::find_program_address(
+                &[POSITION_SEED, leg.market.as_ref(), parlay.owner.as_ref()],
+                &crate::ID,
+            );
+            require_keys_eq!(
+                position_info.key(),
+                expected_position,
+                MarketError::InvalidParlayLegs
+            );
+            let mut position: Account<'info, Position> = Account::try_from(position_info)?;
+            position.open_orders = position.open_orders.saturating_sub(1);
+            // The cost basis was realized when the leg was locked
+            if all_won {
+                realize_proceeds(&mut position, leg.side, 0, amount, 0);
+            }
+            position.exit(&crate::ID)?;
+
+            if amount == 0 {
+                continue;
+            }
+
+            let vault_info = &accounts[1];
+            let (expected_vault, vault_bump) =
+                Pubkey::find_program_address(&[VAULT_SEED, leg.market.as_ref()], &crate::ID);
+            require_keys_eq!(
+                vault_info.key(),
+                expected_vault,
+                MarketError::InvalidParlayLegs
+            );
+            require!(
+                vault_info.lamports() >= amount,
+                MarketError::InsufficientVaultFunds
+            );
+
+            transfer_from_vault(
+                vault_info,
+                &recipient,
+                &ctx.accounts.system_program.to_account_info(),
+                &leg.market,
+                vault_bump,
+                amount,
+            )?;
+            if all_won {
+                total_payout += amount;
+            }
+
+            // Winning shares count toward the market's claimed supply
+            if let Some(mut market) =
+                market.filter(|market| market.status == MarketStatus::Resolved)
+            {
+                market.total_claimed += leg.shares;
+                market.exit(&crate::ID)?;
+            }
+        }
+
+        emit_cpi!(ParlaySettled {
+            parlay: parlay.key(),
+            owner: parlay.owner,
+            parlay_id: parlay.parlay_id,
+            all_won,
+            payout: total_payout,
+        });
+
+        msg!(
+            "Parlay {} settled: {} (paid {} lamports)",
+            parlay.parlay_id,
+            if all_won {
+                "every leg won"
+            } else {
+                "a leg lost"
+            },
+            total_payout
+        );
+        Ok(())
+    }
+
+    /// Close a settled market with its pool and vault, returning rent to the authority
+    ///
+    /// Allowed once every winning share has been claimed and every LP position
+    /// closed, or after `CLOSE_GRACE_PERIOD`. The creation fee must have been
+    /// refunded or forfeited first, and every conditional market on this one
+    /// settled. Whatever is left in the vault is swept to the treasury.
+    ///
+    /// A tombstone keeps the market id from being reused, so positions, orders
+    /// and parlays left behind never re-derive against a new market. Orders keep
+    /// their own escrow and can still be cancelled once the market is closed.
+    pub fn close_market(ctx: Context<CloseMarket>) -> Result<()> {
+        let market = &ctx.accounts.market;
+        require!(
+            market.authority == ctx.accounts.authority.key(),
+            MarketError::Unauthorized
+        );
+        require!(
+            market.creation_fee_paid == 0,
+            MarketError::CreationFeeOutstanding
+        );
+        require!(
+            market.unsettled_children == 0,
+            MarketError::ChildMarketsOutstanding
+        );
+        require!(
+            matches!(
+                market.status,
+                MarketStatus::Resolved | MarketStatus::Cancelled | MarketStatus::Invalid
+            ),
+            MarketError::MarketNotSettled
+        );
+
+        let settled_at = market
+            .resolution_timestamp
+            .ok_or(MarketError::MarketNotSettled)?;
+        let grace_elapsed = Clock::get()?.unix_timestamp >= settled_at + CLOSE_GRACE_PERIOD;
+        if !grace_elapsed {
+            // Refunds are not tracked, so cancelled and invalid markets wait out the grace period
+            let winning_supply = match market.outcome {
+                Some(Outcome::Yes) if market.status == MarketStatus::Resolved => {
+                    market.total_yes_shares
+                }
+                Some(Outcome::No) if market.status == MarketStatus::Resolved => {
+                    market.total_no_shares
+                }
+                _ => return err!(MarketError::ClaimsOutstanding),
+            };
+            require!(
+                market.total_claimed >= winning_supply,
+                MarketError::ClaimsOutstanding
+            );
+        }
+
+        // The pool is closed with the market whenever one was initialized
+        let pool_info = &ctx.accounts.pool;
+        if !pool_info.data_is_empty() {
+            require_keys_eq!(*pool_info.owner, crate::ID, MarketError::InvalidPosition);
+            let pool = Pool::try_deserialize(&mut &pool_info.try_borrow_data()?[..])?;
+            require!(
+                grace_elapsed || (pool.lp_position_count == 0 && pool.creator_fees_accrued == 0),
+                MarketError::ClaimsOutstanding
+            );
+            let rent = pool_info.lamports();
+            pool_info.sub_lamports(rent)?;
+            ctx.accounts.authority.add_lamports(rent)?;
+            pool_info.assign(&system_program::ID);
+            pool_info.resize(0)?;
+        }
+
+        let dust = ctx.accounts.vault.lamports();
+        if dust > 0 {
+            transfer_from_vault(
+                &ctx.accounts.vault,
+                &ctx.accounts.treasury,
+                &ctx.accounts.system_program.to_account_info(),
+                &ctx.accounts.market.key(),
+                ctx.bumps.vault,
+                dust,
+            )?;
+        }
+
+        let closed_market = &mut ctx.accounts.closed_market;
+        closed_market.market = market.key();
+        closed_market.closed_at = Clock::get()?.unix_timestamp;
+        closed_market.bump = ctx.bumps.closed_market;
+
+        emit_cpi!(MarketClosed {
+            market: market.key(),
+            authority: ctx.accounts.authority.key(),
+            swept: dust,
+        });
+
+        msg!(
+            "Market {} closed, {} lamports swept to treasury",
+            hex::encode(market.market_id),
+            dust
+        );
+        Ok(())
+    }
+
+    /// Cancel a market that has not been resolved
+    ///
+    /// Conditional markets must pass their parent market.
+    pub fn cancel_market(ctx: Context<CancelMarket>) -> Result<()> {
+        release_parent(&ctx.accounts.market, &mut ctx.accounts.parent_market)?;
+        let market = &mut ctx.accounts.market;
+        require!(
+            market.status == MarketStatus::Active || market.status == MarketStatus::Halted,
+            MarketError::MarketNotActive
+        );
+        require!(
+            market.authority == ctx.accounts.authority.key(),
+            MarketError::Unauthorized
+        );
+        check_batch_queue_empty(market, &ctx.accounts.batch_auction)?;
+
+        market.status = MarketStatus::Cancelled;
+        market.resolution_timestamp = Some(Clock::get()?.unix_timestamp);
+
+        emit_cpi!(MarketCancelled {
+            market: market.key(),
+            authority: ctx.accounts.authority.key(),
+        });
+
+        msg!("Market cancelled by authority");
+        Ok(())
+    }
+
+    // ========================================
+    // Ephemeral Rollup Functions
+    // ========================================
+
+    /// Delegate market and pool to ephemeral rollup for high-speed trading
+    pub fn delegate_market(ctx: Context<DelegateMarket>) -> Result<()> {
+        require!(
+            ctx.accounts.market.status == MarketStatus::Active,
+            MarketError::MarketNotActive
+        );
+        require!(!ctx.accounts.config.paused, MarketError::ProtocolPaused);
+
+        let validator = ctx.remaining_accounts.first().map(|acc| acc.key());
+        ctx.accounts.delegate_pda(
+            &ctx.accounts.payer,
+            &[MARKET_SEED, ctx.accounts.market.market_id.as_ref()],
+            DelegateConfig {
+                validator,
+                ..Default::default()
+            },
+        )?;
+
+        emit_cpi!(MarketDelegated {
+            market: ctx.accounts.market.key(),
+            validator,
+        });
+
+        msg!("Market delegated to ephemeral rollup");
+        Ok(())
+    }
+
+    /// Delegate a market's order book to the ephemeral rollup for matching
+    pub fn delegate_order_book(ctx: Context<DelegateOrderBook>) -> Result<()> {
+        require!(
+            ctx.accounts.market.status == MarketStatus::Active,
+            MarketError::MarketNotActive
+        );
+
+        let market_key = ctx.accounts.market.key();
+        let validator = ctx.remaining_accounts.first().map(|acc| acc.key());
+        ctx.accounts.delegate_order_book(
+            &ctx.accounts.payer,
+            &[ORDER_BOOK_SEED, market_key.as_ref()],
+            DelegateConfig {
+                validator,
+                ..Default::default()
+            },
+        )?;
+
+        emit_cpi!(AccountDelegated {
+            market: market_key,
+            account: ctx.accounts.order_book.key(),
+            validator,
+        });
+
+        msg!("Order book delegated to ephemeral rollup");
+        Ok(())
+    }
+
+    /// Delegate a market's batch auction queue to the ephemeral rollup
+    pub fn delegate_batch_auction(ctx: Context<DelegateBatchAuction>) -> Result<()> {
+        require!(
+            ctx.accounts.market.status == MarketStatus::Active,
+            MarketError::MarketNotActive
+        );
+
+        let market_key = ctx.accounts.market.key();
+        let validator = ctx.remaining_accounts.first().map(|acc| acc.key());
+        ctx.accounts.delegate_batch_auction(
+            &ctx.accounts.payer,
+            &[BATCH_AUCTION_SEED, market_key.as_ref()],
+            DelegateConfig {
+                validator,
+                ..Default::default()
+            },
+        )?;
+
+        emit_cpi!(AccountDelegated {
+            market: market_key,
+            account: ctx.accounts.batch_auction.key(),
+            validator,
+        });
+
+        msg!("Batch auction delegated to ephemeral rollup");
+        Ok(())
+    }
+
+    /// Delegate a market's price history to the ephemeral rollup so trades there can record it
+    pub fn delegate_price_history(ctx: Context<DelegatePriceHistory>) -> Result<()> {
+        require!(
+            ctx.accounts.market.status == MarketStatus::Active,
+            MarketError::MarketNotActive
+        );
+
+        let market_key = ctx.accounts.market.key();
+        let validator = ctx.remaining_accounts.first().map(|acc| acc.key());
+        ctx.accounts.delegate_price_history(
+            &ctx.accounts.payer,
+            &[PRICE_HISTORY_SEED, market_key.as_ref()],
+            DelegateConfig {
+                validator,
+                ..Default::default()
+            },
+        )?;
+
+        emit_cpi!(AccountDelegated {
+            market: market_key,
+            account: ctx.accounts.price_history.key(),
+            validator,
+        });
+
+        msg!("Price history delegated to ephemeral rollup");
+        Ok(())
+    }
+
+    /// Commit current state from ephemeral rollup to L1
+    pub fn commit_state(ctx: Context<CommitState>) -> Result<()> {
+        let accounts = ctx.accounts.committed_accounts();
+        commit_accounts(
+            &ctx.accounts.payer,
+            accounts.iter().collect(),
+            &ctx.accounts.magic_context,
+            &ctx.accounts.magic_program,
+        )?;
+
+        emit_cpi!(MarketCommitted {
+            market: ctx.accounts.market.key(),
+            undelegated: false,
+        });
+
+        msg!("State committed to L1");
+        Ok(())
+    }
+
+    /// Undelegate market from ephemeral rollup (commit and return to L1)
+    pub fn undelegate_market(ctx: Context<CommitState>) -> Result<()> {
+        let accounts = ctx.accounts.committed_accounts();
+        commit_and_undelegate_accounts(
+            &ctx.accounts.payer,
+            accounts.iter().collect(),
+            &ctx.accounts.magic_context,
+            &ctx.accounts.magic_program,
+        )?;
+
+        emit_cpi!(MarketCommitted {
+            market: ctx.accounts.market.key(),
+            undelegated: true,
+        });
+
+        msg!("Market undelegated from ephemeral rollup");
+        Ok(())
+    }
+}
+
+// ============================================================================
+// Helper Functions
+// ============================================================================
+
+/// Transfer lamports out of a market vault, signing with the vault PDA seeds
+fn transfer_from_vault<'info>(
+    vault: &AccountInfo<'info>,
+    to: &AccountInfo<'info>,
+    system_program: &AccountInfo<'info>,
+    market_key: &Pubkey,
+    vault_bump: u8,
+    amount: u64,
+) -> Result<()> {
+    let bump_slice = &[vault_bump];
+    let seeds = &[VAULT_SEED, market_key.as_ref(), bump_slice];
+    let signer_seeds = &[&seeds[..]];
+
+    let cpi_context = CpiContext::new_with_signer(
+        system_program.clone(),
+        anchor_lang::system_program::Transfer {
+            from: vault.clone(),
+            to: to.clone(),
+        },
+        signer_seeds,
+    );
+    anchor_lang::system_program::transfer(cpi_context, amount)
+}
+
+/// Checks shared by AMM trades and their quotes, so a quote fails wherever the trade would
+fn check_amm_trade(market: &Market, config: &Config) -> Result<()> {
+    require!(
+        market.status == MarketStatus::Active,
+        MarketError::MarketNotActive
+    );
+    require!(!config.paused, MarketError::ProtocolPaused);
+    require!(!market.batch_mode, MarketError::BatchAuctionActive);
+    Ok(())
+}
+
+/// Checks shared by `add_liquidity` and its quote
+fn check_liquidity_open(market: &Market, config: &Config) -> Result<()> {
+    require!(
+        market.status == MarketStatus::Active,
+        MarketError::MarketNotActive
+    );
+    require!(!config.paused, MarketError::ProtocolPaused);
+    Ok(())
+}
+
+/// Outcome of swapping collateral into the pool for shares
+struct BuySwap {
+    shares_out: u64,
+    fee: u64,
+    new_reserve_in: u64,
+    new_reserve_out: u64,
+}
+
+/// Outcome of swapping shares back into the pool for collateral
+struct SellSwap {
+    amount_out: u64,
+    fee: u64,
+    new_reserve_in: u64,
+    new_reserve_out: u64,
+}
+
+/// Metadata may only change while the market is active and untraded, and only by its authority
+fn check_metadata_editable(market: &Market, authority: &Signer) -> Result<()> {
+    require!(
+        market.status == MarketStatus::Active,
+        MarketError::MarketNotActive
+    );
+    require!(
+        market.authority == authority.key(),
+        MarketError::Unauthorized
+    );
+    require!(!market.trading_started, MarketError::MetadataFrozen);
+    Ok(())
+}
+
+/// Check metadata fields against their maximum lengths
+fn validate_metadata(args: &MarketMetadataArgs) -> Result<()> {
+    require!(
+        args.title.len() <= MAX_TITLE_LEN
+            && args.category.len() <= MAX_CATEGORY_LEN
+            && args.tags.len() <= MAX_TAGS
+            && args.tags.iter().all(|tag| tag.len() <= MAX_TAG_LEN)
+            && args.resolution_source.len() <= MAX_RESOLUTION_SOURCE_LEN
+            && args.rules_uri.len() <= MAX_URI_LEN
+            && args.image_uri.len() <= MAX_URI_LEN,
+        MarketError::MetadataTooLong
+    );
+    Ok(())
+}
+
+/// Check economic parameters against the program-wide bounds
+fn validate_market_params(params: &MarketParams) -> Result<()> {
+    require!(
+        params.lp_fee_bps <= MAX_LP_FEE_BPS
+            && params.max_trade_size_bps > 0
+            && params.max_trade_size_bps <= BASIS_POINTS
+            && params.resolution_delay >= 0
+            && params.resolution_delay <= MAX_RESOLUTION_DELAY
+            && params.min_liquidity > 0
+            && params.max_oracle_staleness > 0
+            && params.max_oracle_staleness <= MAX_ORACLE_STALENESS_LIMIT,
+        MarketError::InvalidMarketParams
+    );
+    Ok(())
+}
+
+/// Check a market creation fee and its refund threshold
+///
+/// A nonzero fee must cover the rent-exempt minimum, or the first deposit into
+/// the empty treasury PDA fails. `total_volume` counts every trade, so a creator
+/// can wash trade towards the refund threshold. The protocol fee is the only part
+/// of the trading fee a creator cannot earn back as LP or fee collector, so the
+/// threshold must cost at least the creation fee in protocol fees.
+fn validate_creation_policy(
+    creation_fee: u64,
+    refund_volume: u64,
+    protocol_fee_bps: u64,
+) -> Result<()> {
+    require!(
+        creation_fee == 0 || creation_fee >= Rent::get()?.minimum_balance(0),
+        MarketError::InvalidCreationPolicy
+    );
+    require!(
+        refund_volume == 0
+            || refund_volume as u128 * protocol_fee_bps as u128
+                >= creation_fee as u128 * BASIS_POINTS as u128,
+        MarketError::InvalidCreationPolicy
+    );
+    Ok(())
+}
+
+/// LP tokens minted for depositing `amount` lamports
+fn calculate_lp_tokens_to_mint(pool: &Pool, amount: u64) -> u64 {
+    // lp_tokens = amount * total_lp_shares / total_liquidity
+    // Since we enforce 50/50 added value, we can just use total liquidity
+    if pool.total_liquidity == 0 {
+        amount
+    } else {
+        (amount as u128 * pool.lp_token_supply as u128 / pool.total_liquidity as u128) as u64
+    }
+}
+
+/// Lamports returned for burning `lp_tokens` LP tokens
+fn calculate_liquidity_out(pool: &Pool, lp_tokens: u64) -> Result<u64> {
+    require!(pool.lp_token_supply > 0, MarketError::InsufficientLiquidity);
+
+    // amount = lp_tokens * total_liquidity / total_lp_shares
+    Ok((lp_tokens as u128 * pool.total_liquidity as u128 / pool.lp_token_supply as u128) as u64)
+}
+
+/// Price a buy of `amount_in` lamports of `side` against the constant product curve
+fn calculate_buy(pool: &Pool, market: &Market, side: Outcome, amount_in: u64) -> Result<BuySwap> {
+    // Check for max trade size (a share of total liquidity)
+    require!(
+        amount_in <= pool.total_liquidity * market.params.max_trade_size_bps / BASIS_POINTS,
+        MarketError::TradeExceedsMaxSize
+    );
+    require!(
+        pool.yes_reserve > 0 && pool.no_reserve > 0,
+        MarketError::PoolNotInitialized
+    );
+
+    // Calculate fee
+    let fee = amount_in * market.total_fee_bps() / BASIS_POINTS;
+    let amount_after_fee = amount_in - fee;
+
+    // Calculate shares using constant product formula
+    // For buying YES: shares_out = yes_reserve - (k / (no_reserve + amount))
+    let (reserve_in, reserve_out) = match side {
+        Outcome::Yes => (pool.no_reserve, pool.yes_reserve),
+        Outcome::No => (pool.yes_reserve, pool.no_reserve),
+    };
+
+    let k = reserve_in as u128 * reserve_out as u128;
+    let new_reserve_in = reserve_in + amount_after_fee;
+    let new_reserve_out = (k / new_reserve_in as u128) as u64;
+    let shares_out = reserve_out.saturating_sub(new_reserve_out);
+
+    Ok(BuySwap {
+        shares_out,
+        fee,
+        new_reserve_in,
+        new_reserve_out,
+    })
+}
+
+/// Price a sale of `shares_in` shares of `side` against the constant product curve
+fn calculate_sell(pool: &Pool, market: &Market, side: Outcome, shares_in: u64) -> Result<SellSwap> {
+    require!(
+        pool.yes_reserve > 0 && pool.no_reserve > 0,
+        MarketError::PoolNotInitialized
+    );
+
+    // Calculate output using constant product formula
+    let (reserve_in, reserve_out) = match side {
+        Outcome::Yes => (pool.yes_reserve, pool.no_reserve),
+        Outcome::No => (pool.no_reserve, pool.yes_reserve),
+    };
+
+    let k = reserve_in as u128 * reserve_out as u128;
+    let new_reserve_in = reserve_in + shares_in;
+    let new_reserve_out = (k / new_reserve_in as u128) as u64;
+    let amount_out_before_fee = reserve_out.saturating_sub(new_reserve_out);
+
+    let fee = amount_out_before_fee * market.total_fee_bps() / BASIS_POINTS;
+    let amount_out = amount_out_before_fee - fee;
+
+    Ok(SellSwap {
+        amount_out,
+        fee,
+        new_reserve_in,
+        new_reserve_out,
+    })
+}
+
+/// Price a buy of exactly `shares_out` shares of `side`, returning the lamports required
+fn calculate_buy_exact_out(
+    pool: &Pool,
+    market: &Market,
+    side: Outcome,
+    shares_out: u64,
+) -> Result<(u64, BuySwap)> {
+    require!(
+        pool.yes_reserve > 0 && pool.no_reserve > 0,
+        MarketError::PoolNotInitialized
+    );
+
+    let (reserve_in, reserve_out) = match side {
+        Outcome::Yes => (pool.no_reserve, pool.yes_reserve),
+        Outcome::No => (pool.yes_reserve, pool.no_reserve),
+    };
+    require!(shares_out < reserve_out, MarketError::InsufficientLiquidity);
+    let fee_bps = market.total_fee_bps();
+
+    // Invert the constant product formula, rounding against the trader
+    let k = reserve_in as u128 * reserve_out as u128;
+    let new_reserve_out = reserve_out - shares_out;
+    let required_reserve_in = k.div_ceil(new_reserve_out as u128) as u64;
+    let amount_after_fee = required_reserve_in - reserve_in;
+
+    // Gross up for the fee so that amount_in - fee covers amount_after_fee
+    let amount_in = (amount_after_fee as u128 * BASIS_POINTS as u128)
+        .div_ceil((BASIS_POINTS - fee_bps) as u128) as u64;
+    let fee = amount_in * fee_bps / BASIS_POINTS;
+
+    // Check for max trade size (a share of total liquidity)
+    require!(
+        amount_in <= pool.total_liquidity * market.params.max_trade_size_bps / BASIS_POINTS,
+        MarketError::TradeExceedsMaxSize
+    );
+
+    Ok((
+        amount_in,
+        BuySwap {
+            shares_out,
+            fee,
+            new_reserve_in: reserve_in + (amount_in - fee),
+            new_reserve_out,
+        },
+    ))
+}
+
+/// Price a sale of `side` that pays exactly `amount_out` lamports, returning the shares required
+fn calculate_sell_exact_out(
+    pool: &Pool,
+    market: &Market,
+    side: Outcome,
+    amount_out: u64,
+) -> Result<(u64, SellSwap)> {
+    require!(
+        pool.yes_reserve > 0 && pool.no_reserve > 0,
+        MarketError::PoolNotInitialized
+    );
+
+    let (reserve_in, reserve_out) = match side {
+        Outcome::Yes => (pool.yes_reserve, pool.no_reserve),
+        Outcome::No => (pool.no_reserve, pool.yes_reserve),
+    };
+
+    // Gross up for the fee taken from the output
+    let fee_bps = market.total_fee_bps();
+    let amount_out_before_fee = (amount_out as u128 * BASIS_POINTS as u128)
+        .div_ceil((BASIS_POINTS - fee_bps) as u128) as u64;
+    require!(
+        amount_out_before_fee < reserve_out,
+        MarketError::InsufficientLiquidity
+    );
+
+    // Invert the constant product formula, rounding against the trader
+    let k = reserve_in as u128 * reserve_out as u128;
+    let new_reserve_out = reserve_out - amount_out_before_fee;
+    let new_reserve_in = k.div_ceil(new_reserve_out as u128) as u64;
+    let shares_in = new_reserve_in - reserve_in;
+
+    Ok((
+        shares_in,
+        SellSwap {
+            amount_out,
+            fee: amount_out_before_fee - amount_out,
+            new_reserve_in,
+            new_reserve_out,
+        },
+    ))
+}
+
+/// Split a trading fee between the protocol, the market creator and LPs
+///
+/// LPs receive the remainder after the protocol and creator shares, credited
+/// pro rata through the pool's fee growth accumulator.
+fn accrue_fees(pool: &mut Pool, market: &Market, fee: u64) {
+    // Markets may run fee-free, leaving nothing to split
+    if fee == 0 {
+        return;
+    }
+    let fee_bps = market.total_fee_bps();
+    let protocol_fee = fee * market.protocol_fee_bps / fee_bps;
+    let creator_fee = fee * market.creator_fee_bps / fee_bps;
+    let lp_fee = fee - protocol_fee - creator_fee;
+
+    pool.total_fees_collected += fee;
+    pool.protocol_fees_accrued += protocol_fee;
+    pool.creator_fees_accrued += creator_fee;
+    if pool.lp_token_supply > 0 {
+        pool.fee_growth_per_lp +=
+            lp_fee as u128 * FEE_GROWTH_PRECISION / pool.lp_token_supply as u128;
+    }
+}
+
+/// Bank the fees an LP position earned since its last checkpoint into `fees_owed`
+fn settle_lp_fees(pool: &Pool, lp_position: &mut LPPosition) {
+    let growth = pool.fee_growth_per_lp - lp_position.fee_growth_checkpoint;
+    lp_position.fees_owed += (lp_position.lp_tokens as u128 * growth / FEE_GROWTH_PRECISION) as u64;
+    lp_position.fee_growth_checkpoint = pool.fee_growth_per_lp;
+}
+
+/// Commit a priced buy to the pool reserves and market totals
+fn apply_buy(pool: &mut Pool, market: &mut Market, side: Outcome, swap: &BuySwap) {
+    // Fee stays in the vault outside the reserves math, owed to LPs and fee recipients
+    accrue_fees(pool, market, swap.fee);
+
+    let reserve_in = match side {
+        Outcome::Yes => pool.no_reserve,
+        Outcome::No => pool.yes_reserve,
+    };
+    market.total_volume += swap.new_reserve_in - reserve_in + swap.fee;
+    market.trading_started = true;
+
+    match side {
+        Outcome::Yes => {
+            pool.no_reserve = swap.new_reserve_in;
+            pool.yes_reserve = swap.new_reserve_out;
+            market.total_yes_shares += swap.shares_out;
+        }
+        Outcome::No => {
+            pool.yes_reserve = swap.new_reserve_in;
+            pool.no_reserve = swap.new_reserve_out;
+            market.total_no_shares += swap.shares_out;
+        }
+    }
+}
+
+/// Commit a priced sale of `shares_in` shares to the pool reserves and market totals
+fn apply_sell(
+    pool: &mut Pool,
+    market: &mut Market,
+    side: Outcome,
+    shares_in: u64,
+    swap: &SellSwap,
+) {
+    accrue_fees(pool, market, swap.fee);
+    market.total_volume += swap.amount_out + swap.fee;
+
+    match side {
+        Outcome::Yes => {
+            pool.yes_reserve = swap.new_reserve_in;
+            pool.no_reserve = swap.new_reserve_out;
+            market.total_yes_shares = market.total_yes_shares.saturating_sub(shares_in);
+        }
+        Outcome::No => {
+            pool.no_reserve = swap.new_reserve_in;
+            pool.yes_reserve = swap.new_reserve_out;
+            market.total_no_shares = market.total_no_shares.saturating_sub(shares_in);
+        }
+    }
+}
+
+/// Whether a batch order pays into the NO reserve (YES buys, NO sells) rather than the YES reserve
+fn flows_into_no(order: &BatchOrder) -> bool {
+    matches!(
+        (order.direction, order.side),
+        (OrderDirection::Buy, Outcome::Yes) | (OrderDirection::Sell, Outcome::No)
+    )
+}
+
+/// Clear the included batch orders against the pool at one uniform exchange rate
+///
+/// YES buys and NO sells pay into the NO reserve and are paid from the YES
+/// reserve; NO buys and YES sells the reverse. Every order trades at the rate
+/// `(yes_reserve + yes_in) / (no_reserve + no_in)`, which crosses the two flows
+/// against each other and swaps only their imbalance along the curve, leaving
+/// `k` unchanged up to rounding in the pool's favour. Fees match single trades:
+/// buys pay on lamports in, sells on lamports out.
+///
+/// Returns the updated pool and market, each order's shares (buy) or lamports
+/// (sell), and each order's fee.
+fn price_batch(
+    pool: &Pool,
+    market: &Market,
+    orders: &[BatchOrder],
+    included: &[bool],
+) -> (Pool, Market, Vec<u64>, Vec<u64>) {
+    let mut pool = pool.clone();
+    let mut market = market.clone();
+    let mut outputs = vec![0u64; orders.len()];
+    let mut fees = vec![0u64; orders.len()];
+    let fee_bps = market.total_fee_bps();
+
+    let inputs: Vec<u64> = orders
+        .iter()
+        .map(|order| match order.direction {
+            OrderDirection::Buy => order.amount - order.amount * fee_bps / BASIS_POINTS,
+            OrderDirection::Sell => order.amount,
+        })
+        .collect();
+    let (mut no_in, mut yes_in) = (0u128, 0u128);
+    for i in (0..orders.len()).filter(|&i| included[i]) {
+        if flows_into_no(&orders[i]) {
+            no_in += inputs[i] as u128;
+        } else {
+            yes_in += inputs[i] as u128;
+        }
+    }
+    if no_in + yes_in == 0 {
+        return (pool, market, outputs, fees);
+    }
+
+    let yes_side = pool.yes_reserve as u128 + yes_in;
+    let no_side = pool.no_reserve as u128 + no_in;
+    let (mut yes_out, mut no_out) = (0u128, 0u128);
+    let mut total_fee = 0u64;
+
+    for i in (0..orders.len()).filter(|&i| included[i]) {
+        let order = &orders[i];
+        let gross = if flows_into_no(order) {
+            let gross = inputs[i] as u128 * yes_side / no_side;
+            yes_out += gross;
+            gross as u64
+        } else {
+            let gross = inputs[i] as u128 * no_side / yes_side;
+            no_out += gross;
+            gross as u64
+        };
+
+        match order.direction {
+            OrderDirection::Buy => {
+                outputs[i] = gross;
+                fees[i] = order.amount - inputs[i];
+                total_fee += fees[i];
+                market.total_volume += order.amount;
+                match order.side {
+                    Outcome::Yes => market.total_yes_shares += gross,
+                    Outcome::No => market.total_no_shares += gross,
+                }
+            }
+            OrderDirection::Sell => {
+                let fee = gross * fee_bps / BASIS_POINTS;
+                outputs[i] = gross - fee;
+                fees[i] = fee;
+                total_fee += fee;
+                market.total_volume += gross;
+                match order.side {
+                    Outcome::Yes => {
+                        market.total_yes_shares =
+                            market.total_yes_shares.saturating_sub(order.amount)
+                    }
+                    Outcome::No => {
+                        market.total_no_shares = market.total_no_shares.saturating_sub(order.amount)
+                    }
+                }
+            }
+        }
+    }
+
+    pool.yes_reserve = (yes_side - yes_out) as u64;
+    pool.no_reserve = (no_side - no_out) as u64;
+    accrue_fees(&mut pool, &market, total_fee);
+    market.trading_started = true;
+    (pool, market, outputs, fees)
+}
+
+/// A market in batch mode may only settle once its queue has been cleared or cancelled
+fn check_batch_queue_empty(
+    market: &Market,
+    batch_auction: &Option<Box<Account<BatchAuction>>>,
+) -> Result<()> {
+    if market.batch_mode {
+        require!(
+            batch_auction
+                .as_ref()
+                .is_some_and(|batch| batch.orders.is_empty()),
+            MarketError::BatchNotEmpty
+        );
+    }
+    Ok(())
+}
+
+/// Drop a conditional market that is settling from its parent's unsettled children
+fn release_parent(market: &Market, parent_market: &mut Option<Account<Market>>) -> Result<()> {
+    if let Some(parent_key) = market.parent_market {
+        let parent = parent_market
+            .as_mut()
+            .ok_or(MarketError::InvalidParentMarket)?;
+        require_keys_eq!(parent.key(), parent_key, MarketError::InvalidParentMarket);
+        parent.unsettled_children = parent.unsettled_children.saturating_sub(1);
+    }
+    Ok(())
+}
+
+/// Winning shares a position has not yet claimed on a resolved market
+fn unclaimed_winnings(market: &Market, position: &Position) -> u64 {
+    let winning_shares = match market.outcome {
+        Some(Outcome::Yes) => position.yes_shares,
+        Some(Outcome::No) => position.no_shares,
+        None => 0,
+    };
+    winning_shares.saturating_sub(position.claimed_shares)
+}
+
+/// Add shares to a position, folding `price` into the side's average entry price
+fn credit_position(position: &mut Position, side: Outcome, shares: u64, cost: u64) {
+    let (shares_held, avg_price, total_cost) = match side {
+        Outcome::Yes => (
+            &mut position.yes_shares,
+            &mut position.yes_avg_price,
+            &mut position.yes_cost,
+        ),
+        Outcome::No => (
+            &mut position.no_shares,
+            &mut position.no_avg_price,
+            &mut position.no_cost,
+        ),
+    };
+
+    let new_shares = *shares_held + shares;
+    if new_shares > 0 {
+        *avg_price = ((*avg_price as u128 * *shares_held as u128
+            + cost as u128 * PRICE_DECIMALS as u128)
+            / new_shares as u128) as u64;
+    }
+    *shares_held = new_shares;
+    *total_cost += cost;
+}
+
+/// Book lamports received for `shares` against the position's average entry price
+fn realize_proceeds(position: &mut Position, side: Outcome, shares: u64, proceeds: u64, fee: u64) {
+    let (avg_price, total_proceeds) = match side {
+        Outcome::Yes => (position.yes_avg_price, &mut position.yes_proceeds),
+        Outcome::No => (position.no_avg_price, &mut position.no_proceeds),
+    };
+    let cost_basis = (shares as u128 * avg_price as u128 / PRICE_DECIMALS as u128) as i64;
+
+    *total_proceeds += proceeds;
+    position.realized_pnl += proceeds as i64 - cost_basis;
+    position.fees_paid += fee;
+}
+
+/// Lamports value of `shares` at `price` (scaled by PRICE_DECIMALS)
+fn shares_cost(shares: u64, price: u64) -> u64 {
+    (shares as u128 * price as u128 / PRICE_DECIMALS as u128) as u64
+}
+
+/// Locate one of `owner`'s resting orders on the book
+fn find_book_order(book: &OrderBook, order_id: u64, owner: &Pubkey) -> Result<(BookSide, usize)> {
+    let (book_side, index, order) =
+        if let Some(index) = book.bids.iter().position(|bid| bid.order_id == order_id) {
+            (BookSide::Bid, index, &book.bids[index])
+        } else if let Some(index) = book.asks.iter().position(|ask| ask.order_id == order_id) {
+            (BookSide::Ask, index, &book.asks[index])
+        } else {
+            return err!(MarketError::OrderNotFound);
+        };
+
+    require_keys_eq!(order.owner, *owner, MarketError::Unauthorized);
+    Ok((book_side, index))
+}
+
+/// Pay out a resting order's accumulated fills: YES shares for bids, lamports for asks
+fn settle_book_fills<'info>(
+    book: &mut Account<'info, OrderBook>,
+    position: &mut Position,
+    owner: &AccountInfo<'info>,
+    book_side: BookSide,
+    index: usize,
+) -> Result<()> {
+    let order = match book_side {
+        BookSide::Bid => &mut book.bids[index],
+        BookSide::Ask => &mut book.asks[index],
+    };
+    let filled = order.filled;
+    let price = order.price;
+    order.filled = 0;
+
+    if filled > 0 {
+        match book_side {
+            BookSide::Bid => {
+                credit_position(position, Outcome::Yes, filled, shares_cost(filled, price))
+            }
+            BookSide::Ask => {
+                book.sub_lamports(filled)?;
+                owner.add_lamports(filled)?;
+                let shares = (filled as u128 * PRICE_DECIMALS as u128 / price as u128) as u64;
+                realize_proceeds(position, Outcome::Yes, shares, filled, 0);
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Settle a fully filled order into its owner's position and wallet, then drop it from the book
+///
+/// Lets any trader reclaim a slot held by a filled order its owner has not settled.
+fn evict_filled_book_order<'info>(
+    book: &mut Account<'info, OrderBook>,
+    market_key: &Pubkey,
+    own_position: &Pubkey,
+    book_side: BookSide,
+    position_info: &'info AccountInfo<'info>,
+    owner_info: &'info AccountInfo<'info>,
+) -> Result<()> {
+    // The trader's own position is already loaded; they settle through `settle_book_order`
+    require_keys_neq!(
+        position_info.key(),
+        *own_position,
+        MarketError::InvalidBookAccounts
+    );
+    let mut position: Account<'info, Position> = Account::try_from(position_info)?;
+    require_keys_eq!(
+        position.market,
+        *market_key,
+        MarketError::InvalidBookAccounts
+    );
+    require_keys_eq!(
+        position.user,
+        owner_info.key(),
+        MarketError::InvalidBookAccounts
+    );
+
+    let orders = match book_side {
+        BookSide::Bid => &book.bids,
+        BookSide::Ask => &book.asks,
+    };
+    let index = orders
+        .iter()
+        .position(|order| order.owner == position.user && order.remaining == 0)
+        .ok_or(MarketError::OrderNotFound)?;
+
+    settle_book_fills(book, &mut position, owner_info, book_side, index)?;
+    let order = match book_side {
+        BookSide::Bid => book.bids.remove(index),
+        BookSide::Ask => book.asks.remove(index),
+    };
+    if order.escrow > 0 {
+        book.sub_lamports(order.escrow)?;
+        owner_info.add_lamports(order.escrow)?;
+    }
+    position.open_orders = position.open_orders.saturating_sub(1);
+    position.exit(&crate::ID)?;
+
+    msg!("Filled book order {} evicted", order.order_id);
+    Ok(())
+}
+
+/// Remove sold shares from a position
+///
+/// Callers book any proceeds first with `realize_proceeds`, while the average entry price still applies.
+fn debit_position(position: &mut Position, side: Outcome, shares: u64) {
+    match side {
+        Outcome::Yes => position.yes_shares = position.yes_shares.saturating_sub(shares),
+        Outcome::No => position.no_shares = position.no_shares.saturating_sub(shares),
+    }
+}
+
+/// Add a trade to the market's price history, which must be passed once the market has one
+fn record_price(
+    market: &Market,
+    price_history: &mut Option<Box<Account<PriceHistory>>>,
+    trade: &TradeExecuted,
+) -> Result<()> {
+    match price_history {
+        Some(history) => history.record(
+            Clock::get()?.unix_timestamp,
+            trade.yes_price_before,
+            trade.yes_price_after,
+            trade.amount,
+        ),
+        None => require!(!market.has_price_history, MarketError::PriceHistoryRequired),
+    }
+    Ok(())
+}
+
+/// Describe a trade that moved `pool` from `reserves_before` to its current reserves
+#[allow(clippy::too_many_arguments)]
+fn trade_executed(
+    market: Pubkey,
+    user: Pubkey,
+    pool: &Pool,
+    reserves_before: (u64, u64),
+    side: Outcome,
+    direction: OrderDirection,
+    amount: u64,
+    shares: u64,
+    fee: u64,
+) -> Result<TradeExecuted> {
+    let (yes_reserve_before, no_reserve_before) = reserves_before;
+    let mut pool_before = pool.clone();
+    pool_before.yes_reserve = yes_reserve_before;
+    pool_before.no_reserve = no_reserve_before;
+
+    Ok(TradeExecuted {
+        market,
+        user,
+        side,
+        direction,
+        amount,
+        shares,
+        fee,
+        yes_reserve_before,
+        no_reserve_before,
+        yes_reserve_after: pool.yes_reserve,
+        no_reserve_after: pool.no_reserve,
+        yes_price_before: get_price_for_side(&pool_before, Outcome::Yes)?,
+        yes_price_after: get_price_for_side(pool, Outcome::Yes)?,
+    })
+}
+
+/// Summarise a priced trade, comparing pool prices before and after it
+fn build_trade_quote(
+    pool_before: &Pool,
+    pool_after: &Pool,
+    side: Outcome,
+    amount_in: u64,
+    amount_out: u64,
+    fee: u64,
+) -> Result<TradeQuote> {
+    let price_before = get_price_for_side(pool_before, side)?;
+    let price_after = get_price_for_side(pool_after, side)?;
+    let price_impact_bps = if price_before == 0 {
+        0
+    } else {
+        (price_after.abs_diff(price_before) as u128 * BASIS_POINTS as u128 / price_before as u128)
+            as u64
+    };
+
+    Ok(TradeQuote {
+        amount_in,
+        amount_out,
+        fee,
+        price_before,
+        price_after,
+        price_impact_bps,
+    })
+}
+
+fn get_price_for_side(pool: &Pool, side: Outcome) -> Result<u64> {
+    let total = pool.yes_reserve + pool.no_reserve;
+    if total == 0 {
+        return Ok(PRICE_DECIMALS / 2); // 0.5 default
+    }
+    match side {
+        Outcome::Yes => {
+            Ok((pool.no_reserve as u128 * PRICE_DECIMALS as u128 / total as u128) as u64)
+        }
+        Outcome::No => {
+            Ok((pool.yes_reserve as u128 * PRICE_DECIMALS as u128 / total as u128) as u64)
+        }
+    }
+}
+
+// ============================================================================
+// Account Structs
+// ============================================================================
+
+#[event_cpi]
+#[derive(Accounts)]
+#[instruction(market_id: [u8; 32])]
+pub struct CreateMarket<'info> {
+    #[account(
+        init,
+        payer = authority,
+        space = 8 + Market::INIT_SPACE,
+        seeds = [MARKET_SEED, market_id.as_ref()],
+        bump
+    )]
+    pub market: Account<'info, Market>,
+
+    /// CHECK: Pyth price account - validated by Pyth SDK when reading
+    pub pyth_price_account: AccountInfo<'info>,
+
+    #[account(
+        seeds = [ORACLE_FEED_SEED, pyth_price_account.key().as_ref()],
+        bump = oracle_feed.bump
+    )]
+    pub oracle_feed: Account<'info, OracleRegistryEntry>,
+
+    #[account(seeds = [CONFIG_SEED], bump = config.bump)]
+    pub config: Account<'info, Config>,
+
+    /// Allowlist entry, required while the creator allowlist is enabled
+    #[account(seeds = [CREATOR_SEED, authority.key().as_ref()], bump = creator_entry.bump)]
+    pub creator_entry: Option<Account<'info, CreatorAllowlistEntry>>,
+
+    /// Treasury PDA holding creation fees
+    #[account(mut, seeds = [TREASURY_SEED], bump)]
+    pub treasury: SystemAccount<'info>,
+
+    /// CHECK: Tombstone `close_market` leaves behind; must not exist, so a closed market's id is never reused
+    #[account(
+        seeds = [CLOSED_MARKET_SEED, market_id.as_ref()],
+        bump,
+        constraint = closed_market.data_is_empty() @ MarketError::MarketIdClosed
+    )]
+    pub closed_market: AccountInfo<'info>,
+
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(creator: Pubkey)]
+pub struct AddAllowedCreator<'info> {
+    #[account(seeds = [CONFIG_SEED], bump = config.bump, has_one = admin @ MarketError::Unauthorized)]
+    pub config: Account<'info, Config>,
+
+    #[account(
+        init,
+        payer = admin,
+        space = 8 + CreatorAllowlistEntry::INIT_SPACE,
+        seeds = [CREATOR_SEED, creator.as_ref()],
+        bump
+    )]
+    pub creator_entry: Account<'info, CreatorAllowlistEntry>,
+
+    #[account(mut)]
+    pub admin: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct RemoveAllowedCreator<'info> {
+    #[account(seeds = [CONFIG_SEED], bump = config.bump, has_one = admin @ MarketError::Unauthorized)]
+    pub config: Account<'info, Config>,
+
+    #[account(
+        mut,
+        close = admin,
+        seeds = [CREATOR_SEED, creator_entry.creator.as_ref()],
+        bump = creator_entry.bump
+    )]
+    pub creator_entry: Account<'info, CreatorAllowlistEntry>,
+
+    #[account(mut)]
+    pub admin: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct RefundCreationFee<'info> {
+    #[account(mut)]
+    pub market: Account<'info, Market>,
+
+    #[account(seeds = [CONFIG_SEED], bump = config.bump)]
+    pub config: Account<'info, Config>,
+
+    /// Treasury PDA holding creation fees
+    #[account(mut, seeds = [TREASURY_SEED], bump)]
+    pub treasury: SystemAccount<'info>,
+
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct ForfeitCreationFee<'info> {
+    #[account(mut)]
+    pub market: Account<'info, Market>,
+
+    #[account(seeds = [CONFIG_SEED], bump = config.bump)]
+    pub config: Account<'info, Config>,
+
+    /// Treasury PDA holding creation fees
+    #[account(mut, seeds = [TREASURY_SEED], bump)]
+    pub fee_escrow: SystemAccount<'info>,
+
+    /// CHECK: Forfeit destination, must match the configured treasury
+    #[account(mut, address = config.treasury @ MarketError::Unauthorized)]
+    pub treasury: AccountInfo<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct InitializeConfig<'info> {
+    #[account(
+        init,
+        payer = admin,
+        space = 8 + Config::INIT_SPACE,
+        seeds = [CONFIG_SEED],
+        bump
+    )]
+    pub config: Account<'info, Config>,
+
+    #[account(constraint = program.programdata_address()? == Some(program_data.key()))]
+    pub program: Program<'info, crate::program::PredictionMarket>,
+
+    #[account(
+        constraint = program_data.upgrade_authority_address == Some(admin.key())
+            @ MarketError::Unauthorized
+    )]
+    pub program_data: Account<'info, ProgramData>,
+
+    #[account(mut)]
+    pub admin: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct UpdateConfig<'info> {
+    #[account(
+        mut,
+        seeds = [CONFIG_SEED],
+        bump = config.bump,
+        has_one = admin @ MarketError::Unauthorized
+    )]
+    pub config: Account<'info, Config>,
+
+    pub admin: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct AddOracleFeed<'info> {
+    #[account(seeds = [CONFIG_SEED], bump = config.bump, has_one = admin @ MarketError::Unauthorized)]
+    pub config: Account<'info, Config>,
+
+    #[account(
+        init,
+        payer = admin,
+        space = 8 + OracleRegistryEntry::INIT_SPACE,
+        seeds = [ORACLE_FEED_SEED, pyth_price_account.key().as_ref()],
+        bump
+    )]
+    pub oracle_feed: Account<'info, OracleRegistryEntry>,
+
+    /// CHECK: Pyth price account - validated by Pyth SDK when reading
+    pub pyth_price_account: AccountInfo<'info>,
+
+    #[account(mut)]
+    pub admin: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct UpdateOracleFeed<'info> {
+    #[account(seeds = [CONFIG_SEED], bump = config.bump, has_one = admin @ MarketError::Unauthorized)]
+    pub config: Account<'info, Config>,
+
+    #[account(
+        mut,
+        seeds = [ORACLE_FEED_SEED, oracle_feed.feed.as_ref()],
+        bump = oracle_feed.bump
+    )]
+    pub oracle_feed: Account<'info, OracleRegistryEntry>,
+
+    pub admin: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct RemoveOracleFeed<'info> {
+    #[account(seeds = [CONFIG_SEED], bump = config.bump, has_one = admin @ MarketError::Unauthorized)]
+    pub config: Account<'info, Config>,
+
+    #[account(
+        mut,
+        close = admin,
+        seeds = [ORACLE_FEED_SEED, oracle_feed.feed.as_ref()],
+        bump = oracle_feed.bump
+    )]
+    pub oracle_feed: Account<'info, OracleRegistryEntry>,
+
+    #[account(mut)]
+    pub admin: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct GuardianAction<'info> {
+    #[account(
+        mut,
+        seeds = [CONFIG_SEED],
+        bump = config.bump,
+        has_one = guardian @ MarketError::Unauthorized
+    )]
+    pub config: Account<'info, Config>,
+
+    pub guardian: Signer<'info>,
+}
+
+#[event_cpi]
+#[derive(Accounts)]
+pub struct HaltMarket<'info> {
+    #[account(seeds = [CONFIG_SEED], bump = config.bump)]
+    pub config: Account<'info, Config>,
+
+    #[account(mut)]
+    pub market: Account<'info, Market>,
+
+    pub signer: Signer<'info>,
+}
+
+impl<'info> HaltMarket<'info> {
+    /// The protocol guardian or the market's pauser, returning the signer's role
+    ///
+    /// A signer holding both roles acts as the guardian.
+    fn check_pauser(&self) -> Result<HaltRole> {
+        let signer = self.signer.key();
+        if signer == self.config.guardian {
+            Ok(HaltRole::Guardian)
+        } else if signer == self.market.pauser {
+            Ok(HaltRole::Pauser)
+        } else {
+            err!(MarketError::Unauthorized)
+        }
+    }
+}
+
+#[derive(Accounts)]
+pub struct MarketAuthorityAction<'info> {
+    #[account(mut)]
+    pub market: Account<'info, Market>,
+
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct AcceptAdmin<'info> {
+    #[account(mut, seeds = [CONFIG_SEED], bump = config.bump)]
+    pub config: Account<'info, Config>,
+
+    pub new_admin: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct SetMarketParams<'info> {
+    #[account(mut)]
+    pub market: Account<'info, Market>,
+
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(args: MarketMetadataArgs)]
+pub struct CreateMarketMetadata<'info> {
+    #[account(mut)]
+    pub market: Account<'info, Market>,
+
+    #[account(
+        init,
+        payer = authority,
+        space = MarketMetadata::space(&args),
+        seeds = [METADATA_SEED, market.key().as_ref()],
+        bump
+    )]
+    pub metadata: Account<'info, MarketMetadata>,
+
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(args: MarketMetadataArgs)]
+pub struct UpdateMarketMetadata<'info> {
+    pub market: Account<'info, Market>,
+
+    #[account(
+        mut,
+        seeds = [METADATA_SEED, market.key().as_ref()],
+        bump = metadata.bump,
+        realloc = MarketMetadata::space(&args),
+        realloc::payer = authority,
+        realloc::zero = false
+    )]
+    pub metadata: Account<'info, MarketMetadata>,
+
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct SetCreatorFee<'info> {
+    #[account(mut)]
+    pub market: Account<'info, Market>,
+
+    #[account(seeds = [CONFIG_SEED], bump = config.bump)]
+    pub config: Account<'info, Config>,
+
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct SetParentMarket<'info> {
+    #[account(mut)]
+    pub market: Account<'info, Market>,
+
+    #[account(mut)]
+    pub parent_market: Account<'info, Market>,
+
+    pub authority: Signer<'info>,
+}
+
+#[event_cpi]
+#[derive(Accounts)]
+pub struct InitializePool<'info> {
+    #[account(mut)]
+    pub market: Account<'info, Market>,
+
+    #[account(
+        init,
+        payer = authority,
+        space = 8 + Pool::INIT_SPACE,
+        seeds = [POOL_SEED, market.key().as_ref()],
+        bump
+    )]
+    pub pool: Account<'info, Pool>,
+
+    /// CHECK: Vault PDA for holding SOL
+    #[account(
+        init,
+        payer = authority,
+        space = 0,
+        seeds = [VAULT_SEED, market.key().as_ref()],
+        bump
+    )]
+    pub vault: AccountInfo<'info>,
+
+    #[account(
+        init,
+        payer = authority,
+        space = 8 + LPPosition::INIT_SPACE,
+        seeds = [LP_POSITION_SEED, pool.key().as_ref(), authority.key().as_ref()],
+        bump
+    )]
+    pub lp_position: Account<'info, LPPosition>,
+
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[event_cpi]
+#[derive(Accounts)]
+pub struct ModifyLiquidity<'info> {
+    #[account(mut)]
+    pub market: Account<'info, Market>,
+
+    #[account(seeds = [CONFIG_SEED], bump = config.bump)]
+    pub config: Account<'info, Config>,
+
+    #[account(mut, seeds = [POOL_SEED, market.key().as_ref()], bump = pool.bump)]
+    pub pool: Account<'info, Pool>,
+
+    /// CHECK: Vault PDA
+    #[account(mut, seeds = [VAULT_SEED, market.key().as_ref()], bump)]
+    pub vault: AccountInfo<'info>,
+
+    #[account(
+        init_if_needed,
+        payer = user,
+        space = 8 + LPPosition::INIT_SPACE,
+        seeds = [LP_POSITION_SEED, pool.key().as_ref(), user.key().as_ref()],
+        bump
+    )]
+    pub lp_position: Account<'info, LPPosition>,
+
+    #[account(mut)]
+    pub user: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[event_cpi]
+#[derive(Accounts)]
+pub struct ClaimLpFees<'info> {
+    pub market: Account<'info, Market>,
+
+    #[account(seeds = [POOL_SEED, market.key().as_ref()], bump = pool.bump)]
+    pub pool: Account<'info, Pool>,
+
+    /// CHECK: Vault PDA
+    #[account(mut, seeds = [VAULT_SEED, market.key().as_ref()], bump)]
+    pub vault: AccountInfo<'info>,
+
+    #[account(
+        mut,
+        seeds = [LP_POSITION_SEED, pool.key().as_ref(), user.key().as_ref()],
+        bump = lp_position.bump
+    )]
+    pub lp_position: Account<'info, LPPosition>,
+
+    #[account(mut)]
+    pub user: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[event_cpi]
+#[derive(Accounts)]
+pub struct CollectProtocolFees<'info> {
+    #[account(seeds = [CONFIG_SEED], bump = config.bump)]
+    pub config: Account<'info, Config>,
+
+    pub market: Account<'info, Market>,
+
+    #[account(mut, seeds = [POOL_SEED, market.key().as_ref()], bump = pool.bump)]
+    pub pool: Account<'info, Pool>,
+
+    /// CHECK: Vault PDA
+    #[account(mut, seeds = [VAULT_SEED, market.key().as_ref()], bump)]
+    pub vault: AccountInfo<'info>,
+
+    /// CHECK: Fee destination, must match the configured treasury
+    #[account(mut, address = config.treasury @ MarketError::Unauthorized)]
+    pub treasury: AccountInfo<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[event_cpi]
+#[derive(Accounts)]
+pub struct CollectCreatorFees<'info> {
+    pub market: Account<'info, Market>,
+
+    #[account(mut, seeds = [POOL_SEED, market.key().as_ref()], bump = pool.bump)]
+    pub pool: Account<'info, Pool>,
+
+    /// CHECK: Vault PDA
+    #[account(mut, seeds = [VAULT_SEED, market.key().as_ref()], bump)]
+    pub vault: AccountInfo<'info>,
+
+    #[account(mut)]
+    pub fee_collector: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[event_cpi]
+#[derive(Accounts)]
+pub struct Trade<'info> {
+    #[account(mut)]
+    pub market: Account<'info, Market>,
+
+    #[account(seeds = [CONFIG_SEED], bump = config.bump)]
+    pub config: Account<'info, Config>,
+
+    #[account(mut, seeds = [POOL_SEED, market.key().as_ref()], bump = pool.bump)]
+    pub pool: Account<'info, Pool>,
+
+    /// CHECK: Vault PDA
+    #[account(mut, seeds = [VAULT_SEED, market.key().as_ref()], bump)]
+    pub vault: AccountInfo<'info>,
+
+    #[account(
+        init_if_needed,
+        payer = user,
+        space = 8 + Position::INIT_SPACE,
+        seeds = [POSITION_SEED, market.key().as_ref(), user.key().as_ref()],
+        bump
+    )]
+    pub position: Account<'info, Position>,
+
+    /// Price history, required once the market records one
+    #[account(
+        mut,
+        seeds = [PRICE_HISTORY_SEED, market.key().as_ref()],
+        bump = price_history.bump
+    )]
+    pub price_history: Option<Box<Account<'info, PriceHistory>>>,
+
+    #[account(mut)]
+    pub user: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct InitializePriceHistory<'info> {
+    #[account(mut)]
+    pub market: Account<'info, Market>,
+
+    #[account(
+        init,
+        payer = authority,
+        space = 8 + PriceHistory::INIT_SPACE,
+        seeds = [PRICE_HISTORY_SEED, market.key().as_ref()],
+        bump
+    )]
+    pub price_history: Box<Account<'info, PriceHistory>>,
+
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[event_cpi]
+#[derive(Accounts)]
+#[instruction(side: Outcome, amount: u64, to: Pubkey)]
+pub struct TransferShares<'info> {
+    pub market: Account<'info, Market>,
+
+    #[account(seeds = [CONFIG_SEED], bump = config.bump)]
+    pub config: Account<'info, Config>,
+
+    #[account(
+        mut,
+        seeds = [POSITION_SEED, market.key().as_ref(), user.key().as_ref()],
+        bump = from_position.bump
+    )]
+    pub from_position: Account<'info, Position>,
+
+    #[account(
+        init_if_needed,
+        payer = user,
+        space = 8 + Position::INIT_SPACE,
+        seeds = [POSITION_SEED, market.key().as_ref(), to.as_ref()],
+        bump
+    )]
+    pub to_position: Account<'info, Position>,
+
+    #[account(mut)]
+    pub user: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[event_cpi]
+#[derive(Accounts)]
+#[instruction(order_id: u64)]
+pub struct PlaceLimitOrder<'info> {
+    pub market: Account<'info, Market>,
+
+    #[account(seeds = [CONFIG_SEED], bump = config.bump)]
+    pub config: Account<'info, Config>,
+
+    #[account(
+        init,
+        payer = owner,
+        space = 8 + LimitOrder::INIT_SPACE,
+        seeds = [
+            LIMIT_ORDER_SEED,
+            market.key().as_ref(),
+            owner.key().as_ref(),
+            &order_id.to_le_bytes()
+        ],
+        bump
+    )]
+    pub order: Account<'info, LimitOrder>,
+
+    #[account(
+        init_if_needed,
+        payer = owner,
+        space = 8 + Position::INIT_SPACE,
+        seeds = [POSITION_SEED, market.key().as_ref(), owner.key().as_ref()],
+        bump
+    )]
+    pub position: Account<'info, Position>,
+
+    #[account(mut)]
+    pub owner: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[event_cpi]
+#[derive(Accounts)]
+pub struct FillLimitOrder<'info> {
+    #[account(mut)]
+    pub market: Account<'info, Market>,
+
+    #[account(seeds = [CONFIG_SEED], bump = config.bump)]
+    pub config: Account<'info, Config>,
+
+    #[account(mut, seeds = [POOL_SEED, market.key().as_ref()], bump = pool.bump)]
+    pub pool: Account<'info, Pool>,
+
+    /// CHECK: Vault PDA
+    #[account(mut, seeds = [VAULT_SEED, market.key().as_ref()], bump)]
+    pub vault: AccountInfo<'info>,
+
+    #[account(
+        mut,
+        close = owner,
+        has_one = market,
+        has_one = owner,
+        seeds = [
+            LIMIT_ORDER_SEED,
+            market.key().as_ref(),
+            owner.key().as_ref(),
+            &order.order_id.to_le_bytes()
+        ],
+        bump = order.bump
+    )]
+    pub order: Account<'info, LimitOrder>,
+
+    #[account(
+        mut,
+        seeds = [POSITION_SEED, market.key().as_ref(), owner.key().as_ref()],
+        bump = position.bump
+    )]
+    pub position: Account<'info, Position>,
+
+    /// CHECK: Order owner, receives proceeds and the closed order's rent
+    #[account(mut)]
+    pub owner: AccountInfo<'info>,
+
+    /// Price history, required once the market records one
+    #[account(
+        mut,
+        seeds = [PRICE_HISTORY_SEED, market.key().as_ref()],
+        bump = price_history.bump
+    )]
+    pub price_history: Option<Box<Account<'info, PriceHistory>>>,
+
+    #[account(mut)]
+    pub keeper: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct CancelLimitOrder<'info> {
+    /// CHECK: Market the order belongs to; may already be closed
+    pub market: AccountInfo<'info>,
+
+    #[account(
+        mut,
+        close = owner,
+        has_one = market,
+        has_one = owner,
+        seeds = [
+            LIMIT_ORDER_SEED,
+            market.key().as_ref(),
+            owner.key().as_ref(),
+            &order.order_id.to_le_bytes()
+        ],
+        bump = order.bump
+    )]
+    pub order: Account<'info, LimitOrder>,
+
+    #[account(
+        mut,
+        seeds = [POSITION_SEED, market.key().as_ref(), owner.key().as_ref()],
+        bump = position.bump
+    )]
+    pub position: Account<'info, Position>,
+
+    #[account(mut)]
+    pub owner: Signer<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(trigger_id: u64)]
+pub struct CreateTrigger<'info> {
+    pub market: Account<'info, Market>,
+
+    #[account(seeds = [CONFIG_SEED], bump = config.bump)]
+    pub config: Account<'info, Config>,
+
+    #[account(
+        seeds = [POSITION_SEED, market.key().as_ref(), owner.key().as_ref()],
+        bump = position.bump
+    )]
+    pub position: Account<'info, Position>,
+
+    #[account(
+        init,
+        payer = owner,
+        space = 8 + TriggerOrder::INIT_SPACE,
+        seeds = [TRIGGER_SEED, position.key().as_ref(), &trigger_id.to_le_bytes()],
+        bump
+    )]
+    pub trigger: Account<'info, TriggerOrder>,
+
+    #[account(mut)]
+    pub owner: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[event_cpi]
+#[derive(Accounts)]
+pub struct ExecuteTrigger<'info> {
+    #[account(mut)]
+    pub market: Account<'info, Market>,
+
+    #[account(seeds = [CONFIG_SEED], bump = config.bump)]
+    pub config: Account<'info, Config>,
+
+    #[account(mut, seeds = [POOL_SEED, market.key().as_ref()], bump = pool.bump)]
+    pub pool: Account<'info, Pool>,
+
+    /// CHECK: Vault PDA
+    #[account(mut, seeds = [VAULT_SEED, market.key().as_ref()], bump)]
+    pub vault: AccountInfo<'info>,
+
+    #[account(
+        mut,
+        seeds = [POSITION_SEED, market.key().as_ref(), owner.key().as_ref()],
+        bump = position.bump
+    )]
+    pub position: Account<'info, Position>,
+
+    #[account(
+        mut,
+        close = owner,
+        has_one = market,
+        has_one = owner,
+        has_one = position,
+        seeds = [TRIGGER_SEED, position.key().as_ref(), &trigger.trigger_id.to_le_bytes()],
+        bump = trigger.bump
+    )]
+    pub trigger: Account<'info, TriggerOrder>,
+
+    /// CHECK: Trigger owner, receives proceeds and the closed trigger's rent
+    #[account(mut)]
+    pub owner: AccountInfo<'info>,
+
+    /// Price history, required once the market records one
+    #[account(
+        mut,
+        seeds = [PRICE_HISTORY_SEED, market.key().as_ref()],
+        bump = price_history.bump
+    )]
+    pub price_history: Option<Box<Account<'info, PriceHistory>>>,
+
+    #[account(mut)]
+    pub keeper: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct CancelTrigger<'info> {
+    #[account(
+        mut,
+        close = owner,
+        has_one = owner,
+        seeds = [TRIGGER_SEED, trigger.position.as_ref(), &trigger.trigger_id.to_le_bytes()],
+        bump = trigger.bump
+    )]
+    pub trigger: Account<'info, TriggerOrder>,
+
+    #[account(mut)]
+    pub owner: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct InitializeOrderBook<'info> {
+    pub market: Account<'info, Market>,
+
+    #[account(
+        init,
+        payer = payer,
+        space = 8 + OrderBook::INIT_SPACE,
+        seeds = [ORDER_BOOK_SEED, market.key().as_ref()],
+        bump
+    )]
+    pub order_book: Box<Account<'info, OrderBook>>,
+
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[event_cpi]
+#[derive(Accounts)]
+pub struct PlaceBookOrder<'info> {
+    pub market: Account<'info, Market>,
+
+    #[account(seeds = [CONFIG_SEED], bump = config.bump)]
+    pub config: Account<'info, Config>,
+
+    #[account(
+        mut,
+        seeds = [ORDER_BOOK_SEED, market.key().as_ref()],
+        bump = order_book.bump
+    )]
+    pub order_book: Box<Account<'info, OrderBook>>,
+
+    #[account(
+        init_if_needed,
+        payer = owner,
+        space = 8 + Position::INIT_SPACE,
+        seeds = [POSITION_SEED, market.key().as_ref(), owner.key().as_ref()],
+        bump
+    )]
+    pub position: Account<'info, Position>,
+
+    #[account(mut)]
+    pub owner: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct ManageBookOrder<'info> {
+    /// CHECK: Market the order belongs to; may already be closed
+    pub market: AccountInfo<'info>,
+
+    #[account(
+        mut,
+        seeds = [ORDER_BOOK_SEED, market.key().as_ref()],
+        bump = order_book.bump
+    )]
+    pub order_book: Box<Account<'info, OrderBook>>,
+
+    #[account(
+        mut,
+        seeds = [POSITION_SEED, market.key().as_ref(), owner.key().as_ref()],
+        bump = position.bump
+    )]
+    pub position: Account<'info, Position>,
+
+    #[account(mut)]
+    pub owner: Signer<'info>,
+}
+
+#[event_cpi]
+#[derive(Accounts)]
+pub struct RoutedTrade<'info> {
+    #[account(mut)]
+    pub market: Box<Account<'info, Market>>,
+
+    #[account(seeds = [CONFIG_SEED], bump = config.bump)]
+    pub config: Account<'info, Config>,
+
+    #[account(mut, seeds = [POOL_SEED, market.key().as_ref()], bump = pool.bump)]
+    pub pool: Box<Account<'info, Pool>>,
+
+    /// CHECK: Vault PDA
+    #[account(mut, seeds = [VAULT_SEED, market.key().as_ref()], bump)]
+    pub vault: AccountInfo<'info>,
+
+    #[account(
+        mut,
+        seeds = [ORDER_BOOK_SEED, market.key().as_ref()],
+        bump = order_book.bump
+    )]
+    pub order_book: Box<Account<'info, OrderBook>>,
+
+    #[account(
+        init_if_needed,
+        payer = user,
+        space = 8 + Position::INIT_SPACE,
+        seeds = [POSITION_SEED, market.key().as_ref(), user.key().as_ref()],
+        bump
+    )]
+    pub position: Box<Account<'info, Position>>,
+
+    /// Price history, required once the market records one
+    #[account(
+        mut,
+        seeds = [PRICE_HISTORY_SEED, market.key().as_ref()],
+        bump = price_history.bump
+    )]
+    pub price_history: Option<Box<Account<'info, PriceHistory>>>,
+
+    #[account(mut)]
+    pub user: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct EnableBatchAuction<'info> {
+    #[account(mut)]
+    pub market: Account<'info, Market>,
+
+    #[account(
+        init_if_needed,
+        payer = authority,
+        space = 8 + BatchAuction::INIT_SPACE,
+        seeds = [BATCH_AUCTION_SEED, market.key().as_ref()],
+        bump
+    )]
+    pub batch_auction: Box<Account<'info, BatchAuction>>,
+
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct DisableBatchAuction<'info> {
+    #[account(mut)]
+    pub market: Account<'info, Market>,
+
+    #[account(
+        seeds = [BATCH_AUCTION_SEED, market.key().as_ref()],
+        bump = batch_auction.bump
+    )]
+    pub batch_auction: Box<Account<'info, BatchAuction>>,
+
+    pub authority: Signer<'info>,
+}
+
+#[event_cpi]
+#[derive(Accounts)]
+pub struct SubmitBatchOrder<'info> {
+    pub market: Account<'info, Market>,
+
+    #[account(seeds = [CONFIG_SEED], bump = config.bump)]
+    pub config: Account<'info, Config>,
+
+    #[account(
+        mut,
+        seeds = [BATCH_AUCTION_SEED, market.key().as_ref()],
+        bump = batch_auction.bump
+    )]
+    pub batch_auction: Box<Account<'info, BatchAuction>>,
+
+    #[account(
+        init_if_needed,
+        payer = owner,
+        space = 8 + Position::INIT_SPACE,
+        seeds = [POSITION_SEED, market.key().as_ref(), owner.key().as_ref()],
+        bump
+    )]
+    pub position: Account<'info, Position>,
+
+    #[account(mut)]
+    pub owner: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct CancelBatchOrder<'info> {
+    /// CHECK: Market the order belongs to; may already be closed
+    pub market: AccountInfo<'info>,
+
+    #[account(
+        mut,
+        seeds = [BATCH_AUCTION_SEED, market.key().as_ref()],
+        bump = batch_auction.bump
+    )]
+    pub batch_auction: Box<Account<'info, BatchAuction>>,
+
+    #[account(
+        mut,
+        seeds = [POSITION_SEED, market.key().as_ref(), owner.key().as_ref()],
+        bump = position.bump
+    )]
+    pub position: Account<'info, Position>,
+
+    #[account(mut)]
+    pub owner: Signer<'info>,
+}
+
+#[event_cpi]
+#[derive(Accounts)]
+pub struct ClearBatch<'info> {
+    #[account(mut)]
+    pub market: Box<Account<'info, Market>>,
+
+    #[account(seeds = [CONFIG_SEED], bump = config.bump)]
+    pub config: Account<'info, Config>,
+
+    #[account(mut, seeds = [POOL_SEED, market.key().as_ref()], bump = pool.bump)]
+    pub pool: Box<Account<'info, Pool>>,
+
+    /// CHECK: Vault PDA
+    #[account(mut, seeds = [VAULT_SEED, market.key().as_ref()], bump)]
+    pub vault: AccountInfo<'info>,
+
+    #[account(
+        mut,
+        seeds = [BATCH_AUCTION_SEED, market.key().as_ref()],
+        bump = batch_auction.bump
+    )]
+    pub batch_auction: Box<Account<'info, BatchAuction>>,
+
+    /// Price history, required once the market records one
+    #[account(
+        mut,
+        seeds = [PRICE_HISTORY_SEED, market.key().as_ref()],
+        bump = price_history.bump
+    )]
+    pub price_history: Option<Box<Account<'info, PriceHistory>>>,
+
+    pub cranker: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct ApproveRfqMaker<'info> {
+    pub market: Account<'info, Market>,
+
+    #[account(
+        init,
+        payer = authority,
+        space = 8 + RfqMaker::INIT_SPACE,
+        seeds = [RFQ_MAKER_SEED, market.key().as_ref(), maker.key().as_ref()],
+        bump
+    )]
+    pub rfq_maker: Account<'info, RfqMaker>,
+
+    /// CHECK: Maker wallet being approved
+    pub maker: AccountInfo<'info>,
+
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct RevokeRfqMaker<'info> {
+    pub market: Account<'info, Market>,
+
+    #[account(
+        mut,
+        close = authority,
+        has_one = market,
+        seeds = [RFQ_MAKER_SEED, market.key().as_ref(), rfq_maker.maker.as_ref()],
+        bump = rfq_maker.bump
+    )]
+    pub rfq_maker: Account<'info, RfqMaker>,
+
+    #[account(mut)]
+    pub authority: Signer<'info>,
+}
+
+#[event_cpi]
+#[derive(Accounts)]
+pub struct RfqTrade<'info> {
+    #[account(mut)]
+    pub market: Box<Account<'info, Market>>,
+
+    #[account(seeds = [CONFIG_SEED], bump = config.bump)]
+    pub config: Account<'info, Config>,
+
+    /// Pool, read for the reserves reported in `TradeExecuted`
+    #[account(seeds = [POOL_SEED, market.key().as_ref()], bump = pool.bump)]
+    pub pool: Box<Account<'info, Pool>>,
+
+    /// CHECK: Vault PDA
+    #[account(mut, seeds = [VAULT_SEED, market.key().as_ref()], bump)]
+    pub vault: AccountInfo<'info>,
+
+    #[account(
+        has_one = market,
+        has_one = maker,
+        seeds = [RFQ_MAKER_SEED, market.key().as_ref(), maker.key().as_ref()],
+        bump = rfq_maker.bump
+    )]
+    pub rfq_maker: Account<'info, RfqMaker>,
+
+    #[account(
+        init_if_needed,
+        payer = taker,
+        space = 8 + Position::INIT_SPACE,
+        seeds = [POSITION_SEED, market.key().as_ref(), taker.key().as_ref()],
+        bump
+    )]
+    pub taker_position: Box<Account<'info, Position>>,
+
+    #[account(
+        init_if_needed,
+        payer = maker,
+        space = 8 + Position::INIT_SPACE,
+        seeds = [POSITION_SEED, market.key().as_ref(), maker.key().as_ref()],
+        bump
+    )]
+    pub maker_position: Box<Account<'info, Position>>,
+
+    #[account(mut)]
+    pub taker: Signer<'info>,
+
+    #[account(mut)]
+    pub maker: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+impl<'info> Trade<'info> {
+    /// Add a trade to the market's price history
+    fn record_price(&mut self, trade: &TradeExecuted) -> Result<()> {
+        record_price(&self.market, &mut self.price_history, trade)
+    }
+
+    /// Current YES and NO pool reserves
+    fn reserves(&self) -> (u64, u64) {
+        (self.pool.yes_reserve, self.pool.no_reserve)
+    }
+
+    /// Describe a trade that moved the pool from `reserves_before` to its current reserves
+    fn trade_event(
+        &self,
+        side: Outcome,
+        direction: OrderDirection,
+        amount: u64,
+        shares: u64,
+        fee: u64,
+        reserves_before: (u64, u64),
+    ) -> Result<TradeExecuted> {
+        trade_executed(
+            self.market.key(),
+            self.user.key(),
+            &self.pool,
+            reserves_before,
+            side,
+            direction,
+            amount,
+            shares,
+            fee,
+        )
+    }
 
-        ctx.accounts.delegate_pda(
-            &ctx.accounts.payer,
-            &[MARKET_SEED, ctx.accounts.market.market_id.as_ref()],
-            DelegateConfig {
-                validator: ctx.remaining_accounts.first().map(|acc| acc.key()),
-                ..Default::default()
+    /// Collect `amount_in` from the user and credit a priced buy to the pool and position
+    fn execute_buy(
+        &mut self,
+        side: Outcome,
+        amount_in: u64,
+        swap: &BuySwap,
+        position_bump: u8,
+    ) -> Result<()> {
+        // Transfer SOL to vault
+        let cpi_context = CpiContext::new(
+            self.system_program.to_account_info(),
+            anchor_lang::system_program::Transfer {
+                from: self.user.to_account_info(),
+                to: self.vault.to_account_info(),
             },
-        )?;
+        );
+        anchor_lang::system_program::transfer(cpi_context, amount_in)?;
 
-        msg!("Market delegated to ephemeral rollup");
-        Ok(())
-    }
+        // Update pool state and market totals
+        apply_buy(&mut self.pool, &mut self.market, side, swap);
 
-    /// Commit current state from ephemeral rollup to L1
-    pub fn commit_state(ctx: Context<CommitState>) -> Result<()> {
-        commit_accounts(
-            &ctx.accounts.payer,
-            vec![
-                &ctx.accounts.market.to_account_info(),
-                &ctx.accounts.pool.to_account_info(),
-            ],
-            &ctx.accounts.magic_context,
-            &ctx.accounts.magic_program,
-        )?;
+        // Update or create position
+        let position = &mut self.position;
+        if position.user == Pubkey::default() {
+            position.user = self.user.key();
+            position.market = self.market.key();
+            position.bump = position_bump;
+        }
 
-        msg!("State committed to L1");
+        // Update position shares and cost basis
+        credit_position(position, side, swap.shares_out, amount_in);
+        position.fees_paid += swap.fee;
         Ok(())
     }
 
-    /// Undelegate market from ephemeral rollup (commit and return to L1)
-    pub fn undelegate_market(ctx: Context<CommitState>) -> Result<()> {
-        commit_and_undelegate_accounts(
-            &ctx.accounts.payer,
-            vec![
-                &ctx.accounts.market.to_account_info(),
-                &ctx.accounts.pool.to_account_info(),
-            ],
-            &ctx.accounts.magic_context,
-            &ctx.accounts.magic_program,
+    /// Pay out a priced sale of `shares_in` shares and debit the pool and position
+    fn execute_sell(
+        &mut self,
+        side: Outcome,
+        shares_in: u64,
+        swap: &SellSwap,
+        vault_bump: u8,
+    ) -> Result<()> {
+        // Check vault balance
+        let vault_lamports = self.vault.lamports();
+        require!(
+            vault_lamports >= swap.amount_out,
+            MarketError::InsufficientVaultFunds
+        );
+
+        // Transfer SOL from vault to user
+        transfer_from_vault(
+            &self.vault,
+            &self.user.to_account_info(),
+            &self.system_program.to_account_info(),
+            &self.market.key(),
+            vault_bump,
+            swap.amount_out,
         )?;
 
-        msg!("Market undelegated from ephemeral rollup");
+        // Update pool state and market totals
+        apply_sell(&mut self.pool, &mut self.market, side, shares_in, swap);
+
+        // Update position
+        realize_proceeds(
+            &mut self.position,
+            side,
+            shares_in,
+            swap.amount_out,
+            swap.fee,
+        );
+        debit_position(&mut self.position, side, shares_in);
         Ok(())
     }
 }
 
-// ============================================================================
-// Helper Functions
-// ============================================================================
+#[derive(Accounts)]
+pub struct Quote<'info> {
+    pub market: Box<Account<'info, Market>>,
 
-fn get_price_for_side(pool: &Pool, side: Outcome) -> Result<u64> {
-    let total = pool.yes_reserve + pool.no_reserve;
-    if total == 0 {
-        return Ok(PRICE_DECIMALS / 2); // 0.5 default
-    }
-    match side {
-        Outcome::Yes => {
-            Ok((pool.no_reserve as u128 * PRICE_DECIMALS as u128 / total as u128) as u64)
-        }
-        Outcome::No => {
-            Ok((pool.yes_reserve as u128 * PRICE_DECIMALS as u128 / total as u128) as u64)
-        }
-    }
-}
+    #[account(seeds = [CONFIG_SEED], bump = config.bump)]
+    pub config: Account<'info, Config>,
 
-// ============================================================================
-// Account Structs
-// ============================================================================
+    #[account(seeds = [POOL_SEED, market.key().as_ref()], bump = pool.bump)]
+    pub pool: Box<Account<'info, Pool>>,
+}
 
+#[event_cpi]
 #[derive(Accounts)]
-#[instruction(market_id: [u8; 32])]
-pub struct CreateMarket<'info> {
-    #[account(
-        init,
-        payer = authority,
-        space = 8 + Market::INIT_SPACE,
-        seeds = [MARKET_SEED, market_id.as_ref()],
-        bump
-    )]
+pub struct ResolveMarket<'info> {
+    #[account(mut)]
     pub market: Account<'info, Market>,
 
-    /// CHECK: Pyth price account - validated by Pyth SDK when reading
+    /// CHECK: Pyth price account - validated when reading
+    #[account(constraint = pyth_price_account.key() == market.pyth_price_account)]
     pub pyth_price_account: AccountInfo<'info>,
 
+    #[account(
+        seeds = [ORACLE_FEED_SEED, pyth_price_account.key().as_ref()],
+        bump = oracle_feed.bump
+    )]
+    pub oracle_feed: Account<'info, OracleRegistryEntry>,
+
+    /// Parent market, required when resolving a conditional market
     #[account(mut)]
-    pub authority: Signer<'info>,
+    pub parent_market: Option<Account<'info, Market>>,
 
-    pub system_program: Program<'info, System>,
+    /// Batch queue, required while the market is in batch mode
+    #[account(
+        seeds = [BATCH_AUCTION_SEED, market.key().as_ref()],
+        bump = batch_auction.bump
+    )]
+    pub batch_auction: Option<Box<Account<'info, BatchAuction>>>,
+
+    #[account(mut)]
+    pub resolver: Signer<'info>,
 }
 
+#[event_cpi]
 #[derive(Accounts)]
-pub struct InitializePool<'info> {
+pub struct ResolveMarketManual<'info> {
     #[account(mut)]
     pub market: Account<'info, Market>,
 
+    /// Parent market, required when resolving a conditional market
+    #[account(mut)]
+    pub parent_market: Option<Account<'info, Market>>,
+
+    /// Batch queue, required while the market is in batch mode
     #[account(
-        init,
-        payer = authority,
-        space = 8 + Pool::INIT_SPACE,
-        seeds = [POOL_SEED, market.key().as_ref()],
-        bump
+        seeds = [BATCH_AUCTION_SEED, market.key().as_ref()],
+        bump = batch_auction.bump
     )]
-    pub pool: Account<'info, Pool>,
+    pub batch_auction: Option<Box<Account<'info, BatchAuction>>>,
+
+    pub resolver: Signer<'info>,
+}
+
+#[event_cpi]
+#[derive(Accounts)]
+pub struct ClaimWinnings<'info> {
+    #[account(mut)]
+    pub market: Account<'info, Market>,
+
+    /// CHECK: Vault PDA
+    #[account(mut, seeds = [VAULT_SEED, market.key().as_ref()], bump)]
+    pub vault: AccountInfo<'info>,
+
+    #[account(mut, seeds = [POSITION_SEED, market.key().as_ref(), user.key().as_ref()], bump = position.bump)]
+    pub position: Account<'info, Position>,
+
+    #[account(mut)]
+    pub user: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[event_cpi]
+#[derive(Accounts)]
+#[instruction(parlay_id: u64)]
+pub struct CreateParlay<'info> {
+    #[account(seeds = [CONFIG_SEED], bump = config.bump)]
+    pub config: Account<'info, Config>,
 
-    /// CHECK: Vault PDA for holding SOL
     #[account(
         init,
-        payer = authority,
-        space = 0,
-        seeds = [VAULT_SEED, market.key().as_ref()],
+        payer = owner,
+        space = 8 + Parlay::INIT_SPACE,
+        seeds = [PARLAY_SEED, owner.key().as_ref(), &parlay_id.to_le_bytes()],
         bump
     )]
-    pub vault: AccountInfo<'info>,
+    pub parlay: Account<'info, Parlay>,
+
+    #[account(mut)]
+    pub owner: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
 
+#[event_cpi]
+#[derive(Accounts)]
+pub struct SettleParlay<'info> {
     #[account(
-        init,
-        payer = authority,
-        space = 8 + LPPosition::INIT_SPACE,
-        seeds = [LP_POSITION_SEED, pool.key().as_ref(), authority.key().as_ref()],
-        bump
+        mut,
+        close = owner,
+        has_one = owner,
+        seeds = [PARLAY_SEED, owner.key().as_ref(), &parlay.parlay_id.to_le_bytes()],
+        bump = parlay.bump
     )]
-    pub lp_position: Account<'info, LPPosition>,
+    pub parlay: Account<'info, Parlay>,
 
     #[account(mut)]
-    pub authority: Signer<'info>,
+    pub owner: Signer<'info>,
+
+    #[account(seeds = [CONFIG_SEED], bump = config.bump)]
+    pub config: Account<'info, Config>,
+
+    /// CHECK: Forfeit destination for losing parlays, must match the configured treasury
+    #[account(mut, address = config.treasury @ MarketError::Unauthorized)]
+    pub treasury: AccountInfo<'info>,
 
     pub system_program: Program<'info, System>,
 }
 
+#[event_cpi]
 #[derive(Accounts)]
-pub struct ModifyLiquidity<'info> {
+pub struct ClaimWinningsTo<'info> {
     #[account(mut)]
     pub market: Account<'info, Market>,
 
-    #[account(mut, seeds = [POOL_SEED, market.key().as_ref()], bump = pool.bump)]
-    pub pool: Account<'info, Pool>,
-
     /// CHECK: Vault PDA
     #[account(mut, seeds = [VAULT_SEED, market.key().as_ref()], bump)]
     pub vault: AccountInfo<'info>,
 
-    #[account(
-        init_if_needed,
-        payer = user,
-        space = 8 + LPPosition::INIT_SPACE,
-        seeds = [LP_POSITION_SEED, pool.key().as_ref(), user.key().as_ref()],
-        bump
-    )]
-    pub lp_position: Account<'info, LPPosition>,
+    #[account(mut, seeds = [POSITION_SEED, market.key().as_ref(), user.key().as_ref()], bump = position.bump)]
+    pub position: Account<'info, Position>,
 
+    /// CHECK: Any account may receive winnings
     #[account(mut)]
+    pub recipient: AccountInfo<'info>,
+
     pub user: Signer<'info>,
 
     pub system_program: Program<'info, System>,
 }
 
+#[event_cpi]
 #[derive(Accounts)]
-pub struct Trade<'info> {
+pub struct ClaimMany<'info> {
     #[account(mut)]
-    pub market: Account<'info, Market>,
+    pub user: Signer<'info>,
 
-    #[account(mut, seeds = [POOL_SEED, market.key().as_ref()], bump = pool.bump)]
-    pub pool: Account<'info, Pool>,
+    pub system_program: Program<'info, System>,
+}
+
+#[event_cpi]
+#[derive(Accounts)]
+pub struct CrankClaims<'info> {
+    #[account(mut)]
+    pub market: Account<'info, Market>,
 
     /// CHECK: Vault PDA
     #[account(mut, seeds = [VAULT_SEED, market.key().as_ref()], bump)]
     pub vault: AccountInfo<'info>,
 
+    #[account(mut)]
+    pub cranker: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct ClosePosition<'info> {
+    /// CHECK: Market the position belongs to; may already be closed
+    pub market: AccountInfo<'info>,
+
     #[account(
-        init_if_needed,
-        payer = user,
-        space = 8 + Position::INIT_SPACE,
+        mut,
+        close = user,
         seeds = [POSITION_SEED, market.key().as_ref(), user.key().as_ref()],
-        bump
+        bump = position.bump
     )]
     pub position: Account<'info, Position>,
 
     #[account(mut)]
     pub user: Signer<'info>,
-
-    pub system_program: Program<'info, System>,
 }
 
 #[derive(Accounts)]
-pub struct ResolveMarket<'info> {
+pub struct CloseLpPosition<'info> {
+    /// CHECK: Pool the position belongs to; may already be closed
     #[account(mut)]
-    pub market: Account<'info, Market>,
+    pub pool: AccountInfo<'info>,
 
-    /// CHECK: Pyth price account - validated when reading
-    #[account(constraint = pyth_price_account.key() == market.pyth_price_account)]
-    pub pyth_price_account: AccountInfo<'info>,
+    #[account(
+        mut,
+        close = user,
+        seeds = [LP_POSITION_SEED, pool.key().as_ref(), user.key().as_ref()],
+        bump = lp_position.bump
+    )]
+    pub lp_position: Account<'info, LPPosition>,
 
     #[account(mut)]
-    pub resolver: Signer<'info>,
+    pub user: Signer<'info>,
 }
 
+#[event_cpi]
 #[derive(Accounts)]
-pub struct ClaimWinnings<'info> {
-    #[account(mut)]
-    pub market: Account<'info, Market>,
+pub struct CloseMarket<'info> {
+    #[account(mut, close = authority)]
+    pub market: Box<Account<'info, Market>>,
+
+    /// CHECK: Pool PDA; closed with the market if it was ever initialized
+    #[account(mut, seeds = [POOL_SEED, market.key().as_ref()], bump)]
+    pub pool: AccountInfo<'info>,
+
+    #[account(
+        mut,
+        close = authority,
+        seeds = [METADATA_SEED, market.key().as_ref()],
+        bump = metadata.bump
+    )]
+    pub metadata: Option<Account<'info, MarketMetadata>>,
 
     /// CHECK: Vault PDA
     #[account(mut, seeds = [VAULT_SEED, market.key().as_ref()], bump)]
     pub vault: AccountInfo<'info>,
 
-    #[account(mut, seeds = [POSITION_SEED, market.key().as_ref(), user.key().as_ref()], bump = position.bump)]
-    pub position: Account<'info, Position>,
+    #[account(seeds = [CONFIG_SEED], bump = config.bump)]
+    pub config: Account<'info, Config>,
+
+    /// CHECK: Dust destination, must match the configured treasury
+    #[account(mut, address = config.treasury @ MarketError::Unauthorized)]
+    pub treasury: AccountInfo<'info>,
+
+    #[account(
+        init,
+        payer = authority,
+        space = 8 + ClosedMarket::INIT_SPACE,
+        seeds = [CLOSED_MARKET_SEED, market.market_id.as_ref()],
+        bump
+    )]
+    pub closed_market: Account<'info, ClosedMarket>,
 
     #[account(mut)]
-    pub user: Signer<'info>,
+    pub authority: Signer<'info>,
 
     pub system_program: Program<'info, System>,
 }
 
+#[event_cpi]
 #[derive(Accounts)]
 pub struct CancelMarket<'info> {
     #[account(mut)]
     pub market: Account<'info, Market>,
 
+    /// Parent market, required when cancelling a conditional market
+    #[account(mut)]
+    pub parent_market: Option<Account<'info, Market>>,
+
+    /// Batch queue, required while the market is in batch mode
+    #[account(
+        seeds = [BATCH_AUCTION_SEED, market.key().as_ref()],
+        bump = batch_auction.bump
+    )]
+    pub batch_auction: Option<Box<Account<'info, BatchAuction>>>,
+
     #[account(mut)]
     pub authority: Signer<'info>,
 }
 
+#[event_cpi]
 #[delegate]
 #[derive(Accounts)]
 pub struct DelegateMarket<'info> {
@@ -884,30 +6480,205 @@ pub struct DelegateMarket<'info> {
 
     #[account(mut)]
     pub market: Account<'info, Market>,
+
+    #[account(seeds = [CONFIG_SEED], bump = config.bump)]
+    pub config: Account<'info, Config>,
+}
+
+#[event_cpi]
+#[delegate]
+#[derive(Accounts)]
+pub struct DelegateOrderBook<'info> {
+    pub payer: Signer<'info>,
+
+    /// CHECK: Order book PDA to delegate
+    #[account(mut, del, seeds = [ORDER_BOOK_SEED, market.key().as_ref()], bump)]
+    pub order_book: AccountInfo<'info>,
+
+    pub market: Account<'info, Market>,
+}
+
+#[event_cpi]
+#[delegate]
+#[derive(Accounts)]
+pub struct DelegateBatchAuction<'info> {
+    pub payer: Signer<'info>,
+
+    /// CHECK: Batch auction PDA to delegate
+    #[account(mut, del, seeds = [BATCH_AUCTION_SEED, market.key().as_ref()], bump)]
+    pub batch_auction: AccountInfo<'info>,
+
+    pub market: Account<'info, Market>,
+}
+
+#[event_cpi]
+#[delegate]
+#[derive(Accounts)]
+pub struct DelegatePriceHistory<'info> {
+    pub payer: Signer<'info>,
+
+    /// CHECK: Price history PDA to delegate
+    #[account(mut, del, seeds = [PRICE_HISTORY_SEED, market.key().as_ref()], bump)]
+    pub price_history: AccountInfo<'info>,
+
+    pub market: Account<'info, Market>,
 }
 
+#[event_cpi]
 #[commit]
 #[derive(Accounts)]
 pub struct CommitState<'info> {
     #[account(mut)]
     pub payer: Signer<'info>,
 
-    #[account(mut)]
-    pub market: Account<'info, Market>,
+    #[account(mut)]
+    pub market: Account<'info, Market>,
+
+    #[account(mut, seeds = [POOL_SEED, market.key().as_ref()], bump = pool.bump)]
+    pub pool: Account<'info, Pool>,
+
+    /// Order book, committed alongside the market when it has been delegated
+    #[account(
+        mut,
+        seeds = [ORDER_BOOK_SEED, market.key().as_ref()],
+        bump = order_book.bump
+    )]
+    pub order_book: Option<Box<Account<'info, OrderBook>>>,
+
+    /// Batch auction queue, committed alongside the market when it has been delegated
+    #[account(
+        mut,
+        seeds = [BATCH_AUCTION_SEED, market.key().as_ref()],
+        bump = batch_auction.bump
+    )]
+    pub batch_auction: Option<Box<Account<'info, BatchAuction>>>,
+
+    /// Price history, committed alongside the market when it has been delegated
+    #[account(
+        mut,
+        seeds = [PRICE_HISTORY_SEED, market.key().as_ref()],
+        bump = price_history.bump
+    )]
+    pub price_history: Option<Box<Account<'info, PriceHistory>>>,
+}
 
-    #[account(mut, seeds = [POOL_SEED, market.key().as_ref()], bump = pool.bump)]
-    pub pool: Account<'info, Pool>,
+impl<'info> CommitState<'info> {
+    fn committed_accounts(&self) -> Vec<AccountInfo<'info>> {
+        let mut accounts = vec![self.market.to_account_info(), self.pool.to_account_info()];
+        if let Some(order_book) = &self.order_book {
+            accounts.push(order_book.to_account_info());
+        }
+        if let Some(batch_auction) = &self.batch_auction {
+            accounts.push(batch_auction.to_account_info());
+        }
+        if let Some(price_history) = &self.price_history {
+            accounts.push(price_history.to_account_info());
+        }
+        accounts
+    }
 }
 
 // ============================================================================
 // State Accounts
 // ============================================================================
 
+#[account]
+#[derive(InitSpace)]
+pub struct Config {
+    /// Program admin
+    pub admin: Pubkey,
+    /// Destination for protocol fees
+    pub treasury: Pubkey,
+    /// Protocol fee charged on trades for newly created markets
+    pub protocol_fee_bps: u64,
+    /// Maximum creator fee a market may charge
+    pub max_creator_fee_bps: u64,
+    /// Economic parameters new markets start with
+    pub default_params: MarketParams,
+    /// Proposed admin awaiting acceptance
+    pub pending_admin: Option<Pubkey>,
+    /// Emergency key that can pause the protocol and halt markets
+    pub guardian: Pubkey,
+    /// Whether trading, deposits and delegation are paused globally
+    pub paused: bool,
+    /// Whether only allowlisted creators may create markets
+    pub creator_allowlist_enabled: bool,
+    /// Lamports charged per market creation
+    pub creation_fee: u64,
+    /// AMM volume at which a market's creation fee is refundable (0 disables refunds)
+    pub creation_fee_refund_volume: u64,
+    /// Bump seed
+    pub bump: u8,
+}
+
+#[account]
+#[derive(InitSpace)]
+pub struct CreatorAllowlistEntry {
+    /// Key allowed to create markets
+    pub creator: Pubkey,
+    /// Bump seed
+    pub bump: u8,
+}
+
+#[account]
+#[derive(InitSpace)]
+pub struct OracleRegistryEntry {
+    /// Approved Pyth price account
+    pub feed: Pubkey,
+    /// Human-readable symbol
+    #[max_len(MAX_SYMBOL_LEN)]
+    pub symbol: String,
+    /// Expected price exponent
+    pub exponent: i32,
+    /// Confidence limit used when a market does not set one
+    pub default_max_confidence: u64,
+    /// Whether new markets may use this feed
+    pub can_create: bool,
+    /// Whether markets may resolve from this feed
+    pub can_resolve: bool,
+    /// Bump seed
+    pub bump: u8,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, InitSpace, Debug)]
+pub struct MarketParams {
+    /// Fee paid to LPs on each trade
+    pub lp_fee_bps: u64,
+    /// Largest buy as a share of total liquidity
+    pub max_trade_size_bps: u64,
+    /// Seconds after expiration before resolution is allowed
+    pub resolution_delay: i64,
+    /// Minimum initial pool liquidity
+    pub min_liquidity: u64,
+    /// Maximum oracle price age accepted at resolution, in seconds
+    pub max_oracle_staleness: u64,
+}
+
+impl Default for MarketParams {
+    fn default() -> Self {
+        Self {
+            lp_fee_bps: DEFAULT_LP_FEE_BPS,
+            max_trade_size_bps: DEFAULT_MAX_TRADE_SIZE_BPS,
+            resolution_delay: DEFAULT_RESOLUTION_DELAY,
+            min_liquidity: DEFAULT_MIN_LIQUIDITY,
+            max_oracle_staleness: MAX_ORACLE_STALENESS as u64,
+        }
+    }
+}
+
 #[account]
 #[derive(InitSpace)]
 pub struct Market {
     /// Market creator/authority
     pub authority: Pubkey,
+    /// Proposed authority awaiting acceptance
+    pub pending_authority: Option<Pubkey>,
+    /// May resolve manually if the oracle fails
+    pub resolver: Pubkey,
+    /// Receives creator fees
+    pub fee_collector: Pubkey,
+    /// May halt and resume trading
+    pub pauser: Pubkey,
     /// Unique market identifier
     pub market_id: [u8; 32],
     /// Strike price for resolution (scaled by 10^8 like Pyth)
@@ -933,10 +6704,47 @@ pub struct Market {
     /// Market description
     #[max_len(128)]
     pub description: String,
+    /// Parent market this market is conditional on (if any)
+    pub parent_market: Option<Pubkey>,
+    /// Parent outcome required for this market to resolve normally
+    pub parent_outcome: Option<Outcome>,
+    /// Conditional markets on this one that have not settled yet
+    pub unsettled_children: u32,
+    /// Whether AMM trades must go through the batch auction
+    pub batch_mode: bool,
+    /// Economic parameters, defaulted from the config at creation
+    pub params: MarketParams,
+    /// Protocol fee snapshotted from the config at creation
+    pub protocol_fee_bps: u64,
+    /// Fee paid to the market authority on each trade
+    pub creator_fee_bps: u64,
+    /// Cumulative AMM volume in lamports, fees included
+    pub total_volume: u64,
+    /// Creation fee held in the treasury, zeroed once refunded or forfeited
+    pub creation_fee_paid: u64,
+    /// Volume at which the creation fee is refundable, fixed at creation (0 disables refunds)
+    pub creation_fee_refund_volume: u64,
+    /// Companion metadata account (if any)
+    pub metadata: Option<Pubkey>,
+    /// Winning shares paid out through claims
+    pub total_claimed: u64,
+    /// Set once the first shares are minted and never cleared, even if every share is later sold
+    pub trading_started: bool,
+    /// Role that halted the market, while it is halted
+    pub halted_by: Option<HaltRole>,
+    /// Whether the market records a price history, after which every trade must pass it
+    pub has_price_history: bool,
     /// Bump seed
     pub bump: u8,
 }
 
+impl Market {
+    /// Total fee charged on a trade: LP fee plus protocol and creator fees
+    pub fn total_fee_bps(&self) -> u64 {
+        self.params.lp_fee_bps + self.protocol_fee_bps + self.creator_fee_bps
+    }
+}
+
 #[account]
 #[derive(InitSpace)]
 pub struct Pool {
@@ -950,8 +6758,16 @@ pub struct Pool {
     pub total_liquidity: u64,
     /// Cumulative fees collected
     pub total_fees_collected: u64,
+    /// Cumulative LP fees per LP token (scaled by FEE_GROWTH_PRECISION)
+    pub fee_growth_per_lp: u128,
+    /// Protocol fees awaiting collection to the treasury
+    pub protocol_fees_accrued: u64,
+    /// Creator fees awaiting collection by the market authority
+    pub creator_fees_accrued: u64,
     /// Total LP tokens minted
     pub lp_token_supply: u64,
+    /// LP positions not yet closed
+    pub lp_position_count: u64,
     /// Bump seed
     pub bump: u8,
 }
@@ -965,6 +6781,10 @@ pub struct LPPosition {
     pub pool: Pubkey,
     /// LP tokens owned
     pub lp_tokens: u64,
+    /// Pool fee growth at the last fee settlement
+    pub fee_growth_checkpoint: u128,
+    /// Fees settled but not yet claimed
+    pub fees_owed: u64,
     /// Bump seed
     pub bump: u8,
 }
@@ -984,12 +6804,631 @@ pub struct Position {
     pub yes_avg_price: u64,
     /// Average entry price for NO (scaled by PRICE_DECIMALS)
     pub no_avg_price: u64,
-    /// Whether winnings have been claimed
-    pub claimed: bool,
+    /// Shares already redeemed through claims or refunds
+    pub claimed_shares: u64,
+    /// Total lamports spent acquiring YES shares
+    pub yes_cost: u64,
+    /// Total lamports spent acquiring NO shares
+    pub no_cost: u64,
+    /// Total lamports received for YES shares through sales and claims
+    pub yes_proceeds: u64,
+    /// Total lamports received for NO shares through sales and claims
+    pub no_proceeds: u64,
+    /// Proceeds less the cost basis of every share sold, claimed or refunded
+    pub realized_pnl: i64,
+    /// Total trading and crank fees paid
+    pub fees_paid: u64,
+    /// Limit, book and batch orders and parlay legs that still reference this position
+    pub open_orders: u32,
+    /// Bump seed
+    pub bump: u8,
+}
+
+#[account]
+#[derive(InitSpace)]
+pub struct Parlay {
+    /// Parlay owner
+    pub owner: Pubkey,
+    /// Owner-chosen identifier
+    pub parlay_id: u64,
+    /// Locked legs; the parlay pays only if every non-voided leg wins
+    #[max_len(MAX_PARLAY_LEGS)]
+    pub legs: Vec<ParlayLeg>,
+    /// Unix timestamp when the parlay was created
+    pub created_at: i64,
+    /// Bump seed
+    pub bump: u8,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, InitSpace, Debug)]
+pub struct ParlayLeg {
+    /// Market this leg is in
+    pub market: Pubkey,
+    /// Side of the market this leg backs
+    pub side: Outcome,
+    /// Shares locked from the owner's position
+    pub shares: u64,
+    /// Position average price at lock time, used for void refunds
+    pub entry_price: u64,
+}
+
+/// Frontend-facing market details, sized to its contents
+#[account]
+pub struct MarketMetadata {
+    /// Associated market
+    pub market: Pubkey,
+    /// Human-readable title
+    pub title: String,
+    /// Category, e.g. "crypto"
+    pub category: String,
+    /// Free-form tags
+    pub tags: Vec<String>,
+    /// Where the resolution price comes from, in words
+    pub resolution_source: String,
+    /// URI of the full market rules
+    pub rules_uri: String,
+    /// URI of the market image
+    pub image_uri: String,
+    /// Bump seed
+    pub bump: u8,
+}
+
+impl MarketMetadata {
+    /// Account size, discriminator included, for the given contents
+    pub fn space(args: &MarketMetadataArgs) -> usize {
+        8 + 32
+            + 4
+            + args.title.len()
+            + 4
+            + args.category.len()
+            + 4
+            + args.tags.iter().map(|tag| 4 + tag.len()).sum::<usize>()
+            + 4
+            + args.resolution_source.len()
+            + 4
+            + args.rules_uri.len()
+            + 4
+            + args.image_uri.len()
+            + 1
+    }
+
+    fn apply(&mut self, args: MarketMetadataArgs) {
+        self.title = args.title;
+        self.category = args.category;
+        self.tags = args.tags;
+        self.resolution_source = args.resolution_source;
+        self.rules_uri = args.rules_uri;
+        self.image_uri = args.image_uri;
+    }
+}
+
+/// Contents of a `MarketMetadata` account, see its fields
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, PartialEq, Eq, Debug)]
+pub struct MarketMetadataArgs {
+    /// Human-readable title, at most `MAX_TITLE_LEN` bytes
+    pub title: String,
+    /// Category, at most `MAX_CATEGORY_LEN` bytes
+    pub category: String,
+    /// Up to `MAX_TAGS` tags of at most `MAX_TAG_LEN` bytes each
+    pub tags: Vec<String>,
+    /// Resolution source, at most `MAX_RESOLUTION_SOURCE_LEN` bytes
+    pub resolution_source: String,
+    /// Rules URI, at most `MAX_URI_LEN` bytes
+    pub rules_uri: String,
+    /// Image URI, at most `MAX_URI_LEN` bytes
+    pub image_uri: String,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, Debug)]
+pub struct ParlayLegArgs {
+    /// Side to lock
+    pub side: Outcome,
+    /// Shares to lock
+    pub shares: u64,
+}
+
+#[account]
+#[derive(InitSpace)]
+pub struct LimitOrder {
+    /// Order owner
+    pub owner: Pubkey,
+    /// Associated market
+    pub market: Pubkey,
+    /// Owner-chosen identifier
+    pub order_id: u64,
+    /// Outcome being traded
+    pub side: Outcome,
+    /// Whether the order buys or sells `side`
+    pub direction: OrderDirection,
+    /// Worst average execution price for `side` (scaled by PRICE_DECIMALS)
+    pub limit_price: u64,
+    /// Escrowed lamports (buy) or shares (sell)
+    pub size: u64,
+    /// Unix timestamp after which the order cannot be filled
+    pub expiry: i64,
+    /// Lamports paid to the keeper that fills the order
+    pub keeper_tip: u64,
+    /// Unix timestamp when the order was placed
+    pub created_at: i64,
+    /// Bump seed
+    pub bump: u8,
+}
+
+#[account]
+#[derive(InitSpace)]
+pub struct TriggerOrder {
+    /// Trigger owner
+    pub owner: Pubkey,
+    /// Associated market
+    pub market: Pubkey,
+    /// Position the shares are sold from
+    pub position: Pubkey,
+    /// Owner-chosen identifier
+    pub trigger_id: u64,
+    /// Outcome whose shares are sold
+    pub side: Outcome,
+    /// Stop-loss or take-profit
+    pub kind: TriggerKind,
+    /// Pool price that fires the trigger (scaled by PRICE_DECIMALS)
+    pub trigger_price: u64,
+    /// Shares to sell when fired
+    pub shares: u64,
+    /// Minimum lamports accepted for the sale
+    pub min_amount_out: u64,
+    /// Lamports paid to the keeper that executes the trigger
+    pub keeper_tip: u64,
+    /// Unix timestamp when the trigger was created
+    pub created_at: i64,
+    /// Bump seed
+    pub bump: u8,
+}
+
+#[account]
+#[derive(InitSpace)]
+pub struct OrderBook {
+    /// Associated market
+    pub market: Pubkey,
+    /// Identifier assigned to the next resting order
+    pub next_order_id: u64,
+    /// YES bids, best (highest) price first, then oldest first
+    #[max_len(MAX_BOOK_ORDERS)]
+    pub bids: Vec<BookOrder>,
+    /// YES asks, best (lowest) price first, then oldest first
+    #[max_len(MAX_BOOK_ORDERS)]
+    pub asks: Vec<BookOrder>,
+    /// Bump seed
+    pub bump: u8,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, InitSpace, Debug)]
+pub struct BookOrder {
+    /// Book-assigned identifier
+    pub order_id: u64,
+    /// Order owner
+    pub owner: Pubkey,
+    /// YES price (scaled by PRICE_DECIMALS)
+    pub price: u64,
+    /// YES shares still open
+    pub remaining: u64,
+    /// Unsettled fills: YES shares for bids, lamports for asks
+    pub filled: u64,
+    /// Lamports still escrowed for bids (zero for asks)
+    pub escrow: u64,
+    /// Unix timestamp when the order was placed
+    pub placed_at: i64,
+}
+
+#[account]
+#[derive(InitSpace)]
+pub struct BatchAuction {
+    /// Associated market
+    pub market: Pubkey,
+    /// Seconds each batch stays open for orders
+    pub interval: i64,
+    /// Unix timestamp when the current batch opened
+    pub batch_start: i64,
+    /// Number of batches cleared so far
+    pub batch_number: u64,
+    /// Id assigned to the next queued order
+    pub next_order_id: u64,
+    /// Orders queued for the current batch
+    #[max_len(MAX_BATCH_ORDERS)]
+    pub orders: Vec<BatchOrder>,
+    /// Bump seed
+    pub bump: u8,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, InitSpace, Debug)]
+pub struct BatchOrder {
+    /// Unique within the market's batch queue
+    pub order_id: u64,
+    /// Order owner
+    pub owner: Pubkey,
+    /// Outcome being traded
+    pub side: Outcome,
+    /// Whether the order buys or sells `side`
+    pub direction: OrderDirection,
+    /// Escrowed lamports (buy) or shares (sell)
+    pub amount: u64,
+    /// Minimum shares (buy) or lamports (sell) accepted
+    pub min_out: u64,
+}
+
+#[account]
+#[derive(InitSpace)]
+pub struct PriceHistory {
+    /// Associated market
+    pub market: Pubkey,
+    /// Seconds covered by each candle
+    pub interval: i64,
+    /// Index of the most recent candle in `candles`
+    pub head: u16,
+    /// Ring buffer of YES price candles, oldest overwritten first once full
+    #[max_len(MAX_PRICE_CANDLES)]
+    pub candles: Vec<Candle>,
+    /// Bump seed
+    pub bump: u8,
+}
+
+impl PriceHistory {
+    /// Fold a trade that moved the YES price from `price_before` to `price_after` into its candle
+    pub fn record(&mut self, timestamp: i64, price_before: u64, price_after: u64, volume: u64) {
+        let start_time = timestamp - timestamp.rem_euclid(self.interval);
+
+        if let Some(candle) = self.candles.get_mut(self.head as usize) {
+            if candle.start_time == start_time {
+                candle.high = candle.high.max(price_after);
+                candle.low = candle.low.min(price_after);
+                candle.close = price_after;
+                candle.volume += volume;
+                return;
+            }
+        }
+
+        let candle = Candle {
+            start_time,
+            open: price_before,
+            high: price_before.max(price_after),
+            low: price_before.min(price_after),
+            close: price_after,
+            volume,
+        };
+        if self.candles.len() < MAX_PRICE_CANDLES {
+            self.candles.push(candle);
+            self.head = (self.candles.len() - 1) as u16;
+        } else {
+            self.head = ((self.head as usize + 1) % MAX_PRICE_CANDLES) as u16;
+            self.candles[self.head as usize] = candle;
+        }
+    }
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, InitSpace, Debug)]
+pub struct Candle {
+    /// Unix timestamp the candle opened, aligned to the history interval
+    pub start_time: i64,
+    /// YES price before the candle's first trade (scaled by PRICE_DECIMALS)
+    pub open: u64,
+    /// Highest YES price during the candle
+    pub high: u64,
+    /// Lowest YES price during the candle
+    pub low: u64,
+    /// YES price after the candle's last trade
+    pub close: u64,
+    /// Lamports traded during the candle
+    pub volume: u64,
+}
+
+#[account]
+#[derive(InitSpace)]
+pub struct ClosedMarket {
+    /// Market that held this id
+    pub market: Pubkey,
+    /// Unix timestamp when the market was closed
+    pub closed_at: i64,
+    /// Bump seed
+    pub bump: u8,
+}
+
+#[account]
+#[derive(InitSpace)]
+pub struct RfqMaker {
+    /// Associated market
+    pub market: Pubkey,
+    /// Approved maker wallet
+    pub maker: Pubkey,
+    /// Unix timestamp when the maker was approved
+    pub approved_at: i64,
     /// Bump seed
     pub bump: u8,
 }
 
+// ============================================================================
+// Return Types
+// ============================================================================
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, Debug)]
+pub struct TradeQuote {
+    /// Lamports paid (buy) or shares sold (sell)
+    pub amount_in: u64,
+    /// Shares received (buy) or lamports received after fees (sell)
+    pub amount_out: u64,
+    /// Trading fee in lamports
+    pub fee: u64,
+    /// Pool price of the traded side before the trade (scaled by PRICE_DECIMALS)
+    pub price_before: u64,
+    /// Pool price of the traded side after the trade (scaled by PRICE_DECIMALS)
+    pub price_after: u64,
+    /// Price change caused by the trade, in basis points of `price_before`
+    pub price_impact_bps: u64,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, Debug)]
+pub struct ClaimSummary {
+    /// Market claimed from
+    pub market: Pubkey,
+    /// Winning shares redeemed, zero if the market was skipped
+    pub shares: u64,
+    /// Lamports paid out
+    pub payout: u64,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, Debug)]
+pub struct LiquidityQuote {
+    /// Lamports deposited (add) or withdrawn (remove)
+    pub amount: u64,
+    /// LP tokens minted (add) or burned (remove)
+    pub lp_tokens: u64,
+    /// Share of the LP supply those tokens represent, in basis points
+    pub pool_share_bps: u64,
+}
+
+// ============================================================================
+// Events
+// ============================================================================
+
+#[event]
+pub struct MarketCreated {
+    pub market: Pubkey,
+    pub market_id: [u8; 32],
+    pub authority: Pubkey,
+    pub pyth_price_account: Pubkey,
+    pub strike_price: i64,
+    pub expiration: i64,
+}
+
+#[event]
+pub struct PoolInitialized {
+    pub market: Pubkey,
+    pub pool: Pubkey,
+    pub provider: Pubkey,
+    /// Lamports deposited across both reserves
+    pub liquidity: u64,
+    pub lp_tokens: u64,
+}
+
+#[event]
+pub struct LiquidityAdded {
+    pub market: Pubkey,
+    pub user: Pubkey,
+    /// Lamports deposited
+    pub amount: u64,
+    /// LP tokens minted
+    pub lp_tokens: u64,
+    /// YES reserve after the deposit
+    pub yes_reserve: u64,
+    /// NO reserve after the deposit
+    pub no_reserve: u64,
+}
+
+#[event]
+pub struct LiquidityRemoved {
+    pub market: Pubkey,
+    pub user: Pubkey,
+    /// Lamports withdrawn
+    pub amount: u64,
+    /// LP tokens burned
+    pub lp_tokens: u64,
+    /// YES reserve after the withdrawal
+    pub yes_reserve: u64,
+    /// NO reserve after the withdrawal
+    pub no_reserve: u64,
+}
+
+#[event]
+pub struct TradeExecuted {
+    pub market: Pubkey,
+    pub user: Pubkey,
+    pub side: Outcome,
+    pub direction: OrderDirection,
+    /// Lamports paid (buy) or received (sell)
+    pub amount: u64,
+    /// Shares bought or sold
+    pub shares: u64,
+    /// Trading fee in lamports
+    pub fee: u64,
+    pub yes_reserve_before: u64,
+    pub no_reserve_before: u64,
+    pub yes_reserve_after: u64,
+    pub no_reserve_after: u64,
+    /// YES price before the trade (scaled by PRICE_DECIMALS)
+    pub yes_price_before: u64,
+    /// YES price after the trade (scaled by PRICE_DECIMALS)
+    pub yes_price_after: u64,
+}
+
+#[event]
+pub struct MarketResolved {
+    pub market: Pubkey,
+    /// `Resolved`, or `Invalid` for a conditional market whose condition failed
+    pub status: MarketStatus,
+    pub outcome: Option<Outcome>,
+    /// Oracle price used, `None` for manual resolution and invalidation
+    pub resolution_price: Option<i64>,
+}
+
+#[event]
+pub struct Claimed {
+    pub market: Pubkey,
+    /// Position owner
+    pub user: Pubkey,
+    /// Account the payout was sent to
+    pub recipient: Pubkey,
+    /// Shares redeemed
+    pub shares: u64,
+    /// Lamports paid to the recipient
+    pub payout: u64,
+    /// Crank fee withheld from the payout
+    pub fee: u64,
+}
+
+#[event]
+pub struct MarketCancelled {
+    pub market: Pubkey,
+    pub authority: Pubkey,
+}
+
+#[event]
+pub struct MarketHalted {
+    pub market: Pubkey,
+    pub signer: Pubkey,
+    /// Role the halt was placed under
+    pub role: HaltRole,
+}
+
+#[event]
+pub struct MarketResumed {
+    pub market: Pubkey,
+    pub signer: Pubkey,
+    /// Role the halt was lifted under
+    pub role: HaltRole,
+}
+
+#[event]
+pub struct MarketClosed {
+    pub market: Pubkey,
+    pub authority: Pubkey,
+    /// Vault lamports swept to the treasury
+    pub swept: u64,
+}
+
+#[event]
+pub struct ParlayCreated {
+    pub parlay: Pubkey,
+    pub owner: Pubkey,
+    pub parlay_id: u64,
+    pub legs: Vec<ParlayLeg>,
+}
+
+#[event]
+pub struct ParlaySettled {
+    pub parlay: Pubkey,
+    pub owner: Pubkey,
+    pub parlay_id: u64,
+    /// Whether every non-voided leg won
+    pub all_won: bool,
+    /// Lamports paid to the owner, zero if any leg lost
+    pub payout: u64,
+}
+
+#[event]
+pub struct SharesTransferred {
+    pub market: Pubkey,
+    /// Sending position owner
+    pub from: Pubkey,
+    /// Receiving position owner
+    pub to: Pubkey,
+    pub side: Outcome,
+    pub shares: u64,
+}
+
+#[event]
+pub struct LpFeesClaimed {
+    pub market: Pubkey,
+    pub user: Pubkey,
+    /// Lamports paid to the LP
+    pub amount: u64,
+}
+
+#[event]
+pub struct ProtocolFeesCollected {
+    pub market: Pubkey,
+    pub treasury: Pubkey,
+    /// Lamports swept to the treasury
+    pub amount: u64,
+}
+
+#[event]
+pub struct CreatorFeesCollected {
+    pub market: Pubkey,
+    pub fee_collector: Pubkey,
+    /// Lamports paid to the fee collector
+    pub amount: u64,
+}
+
+#[event]
+pub struct LimitOrderPlaced {
+    pub market: Pubkey,
+    pub owner: Pubkey,
+    pub order_id: u64,
+    pub side: Outcome,
+    pub direction: OrderDirection,
+    /// Worst acceptable average price, fees included (scaled by PRICE_DECIMALS)
+    pub limit_price: u64,
+    /// Lamports to spend (buy) or shares to sell (sell)
+    pub size: u64,
+    pub expiry: i64,
+}
+
+#[event]
+pub struct BookOrderPlaced {
+    pub market: Pubkey,
+    pub owner: Pubkey,
+    pub order_id: u64,
+    pub book_side: BookSide,
+    /// YES price (scaled by PRICE_DECIMALS)
+    pub price: u64,
+    /// YES shares
+    pub size: u64,
+}
+
+#[event]
+pub struct BatchOrderPlaced {
+    pub market: Pubkey,
+    pub owner: Pubkey,
+    pub order_id: u64,
+    /// Batch the order was queued in
+    pub batch_number: u64,
+    pub side: Outcome,
+    pub direction: OrderDirection,
+    /// Lamports to spend (buy) or shares to sell (sell)
+    pub amount: u64,
+    pub min_out: u64,
+}
+
+#[event]
+pub struct MarketDelegated {
+    pub market: Pubkey,
+    /// Validator the market was pinned to, if any
+    pub validator: Option<Pubkey>,
+}
+
+#[event]
+pub struct AccountDelegated {
+    pub market: Pubkey,
+    /// Order book, batch auction or price history delegated alongside the market
+    pub account: Pubkey,
+    /// Validator the account was pinned to, if any
+    pub validator: Option<Pubkey>,
+}
+
+#[event]
+pub struct MarketCommitted {
+    pub market: Pubkey,
+    /// Whether the market was also returned to L1
+    pub undelegated: bool,
+}
+
 // ============================================================================
 // Enums
 // ============================================================================
@@ -999,6 +7438,10 @@ pub enum MarketStatus {
     Active,
     Resolved,
     Cancelled,
+    /// Conditional market whose parent settled against the condition
+    Invalid,
+    /// Trading stopped by the guardian; can be resumed or cancelled
+    Halted,
 }
 
 #[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, InitSpace, Debug)]
@@ -1007,6 +7450,36 @@ pub enum Outcome {
     No,
 }
 
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, InitSpace, Debug)]
+pub enum OrderDirection {
+    Buy,
+    Sell,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, InitSpace, Debug)]
+pub enum BookSide {
+    /// Buy YES
+    Bid,
+    /// Sell YES
+    Ask,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, InitSpace, Debug)]
+pub enum HaltRole {
+    /// The protocol guardian
+    Guardian,
+    /// The market's pauser
+    Pauser,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, InitSpace, Debug)]
+pub enum TriggerKind {
+    /// Sell when the price falls to or below the trigger price
+    StopLoss,
+    /// Sell when the price rises to or above the trigger price
+    TakeProfit,
+}
+
 // ============================================================================
 // Errors
 // ============================================================================
@@ -1053,4 +7526,106 @@ pub enum MarketError {
     MarketCannotBeCancelled,
     #[msg("Output amount too small")]
     OutputTooSmall,
+    #[msg("Invalid parent market")]
+    InvalidParentMarket,
+    #[msg("Parent market has not been resolved")]
+    ParentNotResolved,
+    #[msg("Trading has already started")]
+    TradingAlreadyStarted,
+    #[msg("Market is not refundable")]
+    MarketNotRefundable,
+    #[msg("No refund to claim")]
+    NoRefund,
+    #[msg("Invalid parlay legs")]
+    InvalidParlayLegs,
+    #[msg("Parlay legs must be in distinct markets")]
+    DuplicateParlayMarket,
+    #[msg("Invalid limit price")]
+    InvalidLimitPrice,
+    #[msg("Order has expired")]
+    OrderExpired,
+    #[msg("Pool price has not reached the limit")]
+    LimitPriceNotReached,
+    #[msg("Pool price has not reached the trigger")]
+    TriggerNotReached,
+    #[msg("Order book side is full")]
+    OrderBookFull,
+    #[msg("Order book side is full of filled orders; settle them first")]
+    BookNeedsSettlement,
+    #[msg("Eviction accounts must be (position, owner) pairs for filled orders")]
+    InvalidBookAccounts,
+    #[msg("Order would cross the book")]
+    OrderWouldCross,
+    #[msg("Order not found")]
+    OrderNotFound,
+    #[msg("Market is in batch auction mode")]
+    BatchAuctionActive,
+    #[msg("Market is not in batch auction mode")]
+    BatchAuctionInactive,
+    #[msg("Invalid batch interval")]
+    InvalidBatchInterval,
+    #[msg("Batch queue is full")]
+    BatchQueueFull,
+    #[msg("Batch queue is not empty")]
+    BatchNotEmpty,
+    #[msg("Batch window has closed; clear it first")]
+    BatchWindowClosed,
+    #[msg("Batch window is still open")]
+    BatchWindowOpen,
+    #[msg("Invalid batch clearing accounts")]
+    InvalidBatchAccounts,
+    #[msg("Quote has expired")]
+    QuoteExpired,
+    #[msg("No fees to claim")]
+    NoFeesToClaim,
+    #[msg("Fee exceeds the allowed maximum")]
+    FeeTooHigh,
+    #[msg("Market parameters out of bounds")]
+    InvalidMarketParams,
+    #[msg("Protocol is paused")]
+    ProtocolPaused,
+    #[msg("Market is not halted")]
+    MarketNotHalted,
+    #[msg("Manual resolution grace period has not elapsed")]
+    ManualResolutionTooEarly,
+    #[msg("Oracle feed is not registered for this action")]
+    OracleFeedNotPermitted,
+    #[msg("Oracle price exponent does not match the registry")]
+    OracleExponentMismatch,
+    #[msg("Symbol too long")]
+    SymbolTooLong,
+    #[msg("Creator is not on the allowlist")]
+    CreatorNotAllowed,
+    #[msg("Market volume below the refund threshold")]
+    VolumeThresholdNotMet,
+    #[msg("Metadata is frozen once trading starts")]
+    MetadataFrozen,
+    #[msg("Metadata field too long")]
+    MetadataTooLong,
+    #[msg("Market is not settled")]
+    MarketNotSettled,
+    #[msg("Claims or LP positions are still outstanding")]
+    ClaimsOutstanding,
+    #[msg("Position is not settled")]
+    PositionNotSettled,
+    #[msg("Invalid claim accounts")]
+    InvalidClaimAccounts,
+    #[msg("Invalid candle interval")]
+    InvalidCandleInterval,
+    #[msg("Market records a price history that was not passed")]
+    PriceHistoryRequired,
+    #[msg("Too many markets in one claim")]
+    TooManyClaims,
+    #[msg("Position has open orders or parlay legs")]
+    OrdersOutstanding,
+    #[msg("Refund volume must cost at least the creation fee in protocol fees")]
+    InvalidCreationPolicy,
+    #[msg("Creation fee is still refundable")]
+    RefundAvailable,
+    #[msg("Creation fee must be refunded or forfeited first")]
+    CreationFeeOutstanding,
+    #[msg("Market id belongs to a closed market")]
+    MarketIdClosed,
+    #[msg("Conditional markets on this market have not settled")]
+    ChildMarketsOutstanding,
 }
@@ -48,6 +48,10 @@ describe("prediction_market", () => {
   let poolPDA: PublicKey;
   let vaultPDA: PublicKey;
   let positionPDA: PublicKey;
+  let configPDA: PublicKey;
+  let oracleFeedPDA: PublicKey;
+  let treasuryPDA: PublicKey;
+  let priceHistoryPDA: PublicKey;
 
   before(async function () {
     // Log balance
@@ -78,169 +82,1955 @@ describe("prediction_market", () => {
       program.programId
     );
     console.log("Position PDA:", positionPDA.toString());
+
+    [configPDA] = PublicKey.findProgramAddressSync(
+      [Buffer.from("config")],
+      program.programId
+    );
+    console.log("Config PDA:", configPDA.toString());
+
+    [oracleFeedPDA] = PublicKey.findProgramAddressSync(
+      [Buffer.from("oracle_feed"), mockPythPriceAccount.publicKey.toBuffer()],
+      program.programId
+    );
+    console.log("Oracle Feed PDA:", oracleFeedPDA.toString());
+
+    [treasuryPDA] = PublicKey.findProgramAddressSync(
+      [Buffer.from("treasury")],
+      program.programId
+    );
+    console.log("Treasury PDA:", treasuryPDA.toString());
+
+    [priceHistoryPDA] = PublicKey.findProgramAddressSync(
+      [Buffer.from("price_history"), marketPDA.toBuffer()],
+      program.programId
+    );
+    console.log("Price History PDA:", priceHistoryPDA.toString());
   });
 
+  // Create a market with a funded pool, returning its PDAs
+  async function setupMarket(id: number) {
+    const id32 = new Uint8Array(32);
+    id32[0] = id;
+    const [market] = PublicKey.findProgramAddressSync(
+      [Buffer.from("market"), Buffer.from(id32)],
+      program.programId
+    );
+    const [pool] = PublicKey.findProgramAddressSync(
+      [Buffer.from("pool"), market.toBuffer()],
+      program.programId
+    );
+    const [vault] = PublicKey.findProgramAddressSync(
+      [Buffer.from("vault"), market.toBuffer()],
+      program.programId
+    );
+    const [position] = PublicKey.findProgramAddressSync(
+      [Buffer.from("position"), market.toBuffer(), authority.publicKey.toBuffer()],
+      program.programId
+    );
+
+    await program.methods
+      .createMarket(Array.from(id32), strikePrice, expiration, maxConfidence, `Test market ${id}`)
+      .accounts({
+        market,
+        pythPriceAccount: mockPythPriceAccount.publicKey,
+        oracleFeed: oracleFeedPDA,
+        config: configPDA,
+        creatorEntry: null,
+        treasury: treasuryPDA,
+        authority: authority.publicKey,
+        systemProgram: SystemProgram.programId,
+      })
+      .rpc();
+    await program.methods
+      .initializePool(new BN(1_000_000_000))
+      .accounts({
+        market,
+        pool,
+        vault,
+        authority: authority.publicKey,
+        systemProgram: SystemProgram.programId,
+      })
+      .rpc();
+
+    return { market, pool, vault, position };
+  }
+
+  // Buy `side` shares in a market created by `setupMarket`
+  async function buyIn(
+    pdas: { market: PublicKey; pool: PublicKey; vault: PublicKey; position: PublicKey },
+    side: any,
+    amountIn: BN
+  ) {
+    await program.methods
+      .buyShares(side, amountIn, new BN(1))
+      .accounts({
+        market: pdas.market,
+        config: configPDA,
+        pool: pdas.pool,
+        vault: pdas.vault,
+        position: pdas.position,
+        priceHistory: null,
+        user: authority.publicKey,
+        systemProgram: SystemProgram.programId,
+      })
+      .rpc();
+  }
+
   // ========================================
   // Market Lifecycle Tests
   // ========================================
 
-  describe("Market Creation", () => {
-    it("creates a new prediction market", async () => {
-      const start = Date.now();
-      const tx = await program.methods
-        .createMarket(
-          Array.from(marketId),
-          strikePrice,
-          expiration,
-          maxConfidence,
-          description
-        )
+  describe("Market Creation", () => {
+    it("initializes the program config", async () => {
+      const [programDataPDA] = PublicKey.findProgramAddressSync(
+        [program.programId.toBuffer()],
+        new PublicKey("BPFLoaderUpgradeab1e11111111111111111111111")
+      );
+
+      const tx = await program.methods
+        .initializeConfig(authority.publicKey, new BN(10), new BN(100))
+        .accounts({
+          config: configPDA,
+          program: program.programId,
+          programData: programDataPDA,
+          admin: authority.publicKey,
+          systemProgram: SystemProgram.programId,
+        })
+        .rpc({ skipPreflight: true });
+      console.log("Initialize Config tx:", tx);
+
+      const config = await program.account.config.fetch(configPDA);
+      expect(config.admin.toBase58()).to.equal(authority.publicKey.toBase58());
+      expect(config.protocolFeeBps.toNumber()).to.equal(10);
+    });
+
+    it("updates the fee config and rejects fees above the ceiling", async () => {
+      await program.methods
+        .updateFeeConfig(authority.publicKey, new BN(10), new BN(150))
+        .accounts({ config: configPDA, admin: authority.publicKey })
+        .rpc();
+      const config = await program.account.config.fetch(configPDA);
+      expect(config.maxCreatorFeeBps.toNumber()).to.equal(150);
+
+      try {
+        await program.methods
+          .updateFeeConfig(authority.publicKey, new BN(101), new BN(100))
+          .accounts({ config: configPDA, admin: authority.publicKey })
+          .rpc();
+        expect.fail("Should have thrown FeeTooHigh");
+      } catch (err: any) {
+        expect(err.toString()).to.include("FeeTooHigh");
+      }
+
+      await program.methods
+        .updateFeeConfig(authority.publicKey, new BN(10), new BN(100))
+        .accounts({ config: configPDA, admin: authority.publicKey })
+        .rpc();
+    });
+
+    it("rejects default params with an oracle staleness above the limit", async () => {
+      const config = await program.account.config.fetch(configPDA);
+      try {
+        await program.methods
+          .updateDefaultParams({ ...config.defaultParams, maxOracleStaleness: new BN(3601) })
+          .accounts({ config: configPDA, admin: authority.publicKey })
+          .rpc();
+        expect.fail("Should have thrown InvalidMarketParams");
+      } catch (err: any) {
+        expect(err.toString()).to.include("InvalidMarketParams");
+      }
+    });
+
+    it("rejects a refund volume cheaper to wash trade than the creation fee", async () => {
+      // 10 bps protocol fee: 1 SOL of fees needs at least 1000 SOL of volume
+      try {
+        await program.methods
+          .setCreationPolicy(false, new BN(LAMPORTS_PER_SOL), new BN(100 * LAMPORTS_PER_SOL))
+          .accounts({ config: configPDA, admin: authority.publicKey })
+          .rpc();
+        expect.fail("Should have thrown InvalidCreationPolicy");
+      } catch (err: any) {
+        expect(err.toString()).to.include("InvalidCreationPolicy");
+      }
+
+      // Lowering the protocol fee must not make an existing threshold cheap again
+      await program.methods
+        .setCreationPolicy(false, new BN(LAMPORTS_PER_SOL), new BN(1000 * LAMPORTS_PER_SOL))
+        .accounts({ config: configPDA, admin: authority.publicKey })
+        .rpc();
+      try {
+        await program.methods
+          .updateFeeConfig(authority.publicKey, new BN(5), new BN(100))
+          .accounts({ config: configPDA, admin: authority.publicKey })
+          .rpc();
+        expect.fail("Should have thrown InvalidCreationPolicy");
+      } catch (err: any) {
+        expect(err.toString()).to.include("InvalidCreationPolicy");
+      }
+
+      await program.methods
+        .setCreationPolicy(false, new BN(0), new BN(0))
+        .accounts({ config: configPDA, admin: authority.publicKey })
+        .rpc();
+      const config = await program.account.config.fetch(configPDA);
+      expect(config.creationFee.toNumber()).to.equal(0);
+    });
+
+    it("rejects a creation fee below the rent-exempt minimum", async () => {
+      try {
+        await program.methods
+          .setCreationPolicy(false, new BN(1), new BN(0))
+          .accounts({ config: configPDA, admin: authority.publicKey })
+          .rpc();
+        expect.fail("Should have thrown InvalidCreationPolicy");
+      } catch (err: any) {
+        expect(err.toString()).to.include("InvalidCreationPolicy");
+      }
+    });
+
+    it("transfers the admin role in two steps", async () => {
+      const newAdmin = Keypair.generate();
+
+      await program.methods
+        .transferAdmin(newAdmin.publicKey)
+        .accounts({ config: configPDA, admin: authority.publicKey })
+        .rpc();
+      let config = await program.account.config.fetch(configPDA);
+      expect(config.admin.toBase58()).to.equal(authority.publicKey.toBase58());
+      expect(config.pendingAdmin.toBase58()).to.equal(newAdmin.publicKey.toBase58());
+
+      // Only the proposed admin can accept
+      const intruder = Keypair.generate();
+      try {
+        await program.methods
+          .acceptAdmin()
+          .accounts({ config: configPDA, newAdmin: intruder.publicKey })
+          .signers([intruder])
+          .rpc();
+        expect.fail("Should have thrown Unauthorized");
+      } catch (err: any) {
+        expect(err.toString()).to.include("Unauthorized");
+      }
+
+      await program.methods
+        .acceptAdmin()
+        .accounts({ config: configPDA, newAdmin: newAdmin.publicKey })
+        .signers([newAdmin])
+        .rpc();
+      config = await program.account.config.fetch(configPDA);
+      expect(config.admin.toBase58()).to.equal(newAdmin.publicKey.toBase58());
+      expect(config.pendingAdmin).to.be.null;
+
+      // The old admin has lost its rights; hand the role back for the rest of the suite
+      try {
+        await program.methods
+          .transferAdmin(authority.publicKey)
+          .accounts({ config: configPDA, admin: authority.publicKey })
+          .rpc();
+        expect.fail("Should have thrown Unauthorized");
+      } catch (err: any) {
+        expect(err.toString()).to.include("Unauthorized");
+      }
+      await program.methods
+        .transferAdmin(authority.publicKey)
+        .accounts({ config: configPDA, admin: newAdmin.publicKey })
+        .signers([newAdmin])
+        .rpc();
+      await program.methods
+        .acceptAdmin()
+        .accounts({ config: configPDA, newAdmin: authority.publicKey })
+        .rpc();
+      config = await program.account.config.fetch(configPDA);
+      expect(config.admin.toBase58()).to.equal(authority.publicKey.toBase58());
+    });
+
+    it("registers the oracle feed", async () => {
+      const tx = await program.methods
+        .addOracleFeed("SOL/USD", -8, maxConfidence, true, true)
+        .accounts({
+          config: configPDA,
+          oracleFeed: oracleFeedPDA,
+          pythPriceAccount: mockPythPriceAccount.publicKey,
+          admin: authority.publicKey,
+          systemProgram: SystemProgram.programId,
+        })
+        .rpc({ skipPreflight: true });
+      console.log("Add Oracle Feed tx:", tx);
+
+      const feed = await program.account.oracleRegistryEntry.fetch(oracleFeedPDA);
+      expect(feed.symbol).to.equal("SOL/USD");
+      expect(feed.canCreate).to.equal(true);
+    });
+
+    it("creates a new prediction market", async () => {
+      const start = Date.now();
+      const tx = await program.methods
+        .createMarket(
+          Array.from(marketId),
+          strikePrice,
+          expiration,
+          maxConfidence,
+          description
+        )
+        .accounts({
+          market: marketPDA,
+          pythPriceAccount: mockPythPriceAccount.publicKey,
+          oracleFeed: oracleFeedPDA,
+          config: configPDA,
+          creatorEntry: null,
+          treasury: treasuryPDA,
+          authority: authority.publicKey,
+          systemProgram: SystemProgram.programId,
+        })
+        .rpc({ skipPreflight: true });
+
+      const duration = Date.now() - start;
+      console.log(`${duration}ms - Create Market tx: ${tx}`);
+
+      // Verify market state
+      const market = await program.account.market.fetch(marketPDA);
+      expect(market.authority.toBase58()).to.equal(authority.publicKey.toBase58());
+      expect(market.strikePrice.toNumber()).to.equal(strikePrice.toNumber());
+      expect(market.description).to.equal(description);
+      expect(market.status).to.deep.equal({ active: {} });
+      expect(market.pythPriceAccount.toBase58()).to.equal(mockPythPriceAccount.publicKey.toBase58());
+    });
+  });
+
+  describe("Liquidity Pool", () => {
+    it("initializes the liquidity pool", async () => {
+      const initialLiquidity = new BN(1_000_000_000); // 1 SOL
+
+      const start = Date.now();
+      const tx = await program.methods
+        .initializePool(initialLiquidity)
+        .accounts({
+          market: marketPDA,
+          pool: poolPDA,
+          vault: vaultPDA,
+          authority: authority.publicKey,
+          systemProgram: SystemProgram.programId,
+        })
+        .rpc({ skipPreflight: true });
+
+      const duration = Date.now() - start;
+      console.log(`${duration}ms - Initialize Pool tx: ${tx}`);
+
+      // Verify pool state
+      const pool = await program.account.pool.fetch(poolPDA);
+      expect(pool.market.toBase58()).to.equal(marketPDA.toBase58());
+      expect(pool.yesReserve.toNumber()).to.equal(initialLiquidity.toNumber());
+      expect(pool.noReserve.toNumber()).to.equal(initialLiquidity.toNumber());
+      expect(pool.totalLiquidity.toNumber()).to.equal(initialLiquidity.toNumber() * 2);
+    });
+
+    it("allows adding more liquidity", async () => {
+      const addAmount = new BN(500_000_000); // 0.5 SOL
+
+      const poolBefore = await program.account.pool.fetch(poolPDA);
+
+      const tx = await program.methods
+        .addLiquidity(addAmount)
+        .accounts({
+          market: marketPDA,
+          config: configPDA,
+          pool: poolPDA,
+          vault: vaultPDA,
+          user: authority.publicKey,
+          systemProgram: SystemProgram.programId,
+        })
+        .rpc({ skipPreflight: true });
+
+      console.log("Add Liquidity tx:", tx);
+
+      const poolAfter = await program.account.pool.fetch(poolPDA);
+      expect(poolAfter.totalLiquidity.toNumber()).to.be.greaterThan(
+        poolBefore.totalLiquidity.toNumber()
+      );
+    });
+
+    it("initializes the price history", async () => {
+      const interval = new BN(60); // 1 minute candles
+
+      const tx = await program.methods
+        .initializePriceHistory(interval)
+        .accounts({
+          market: marketPDA,
+          priceHistory: priceHistoryPDA,
+          authority: authority.publicKey,
+          systemProgram: SystemProgram.programId,
+        })
+        .rpc({ skipPreflight: true });
+
+      console.log("Initialize Price History tx:", tx);
+
+      const history = await program.account.priceHistory.fetch(priceHistoryPDA);
+      expect(history.market.toBase58()).to.equal(marketPDA.toBase58());
+      expect(history.interval.toNumber()).to.equal(60);
+      expect(history.candles).to.have.lengthOf(0);
+    });
+  });
+
+  describe("Trading (AMM)", () => {
+    it("buys YES shares", async () => {
+      const amountIn = new BN(100_000_000); // 0.1 SOL
+      const minSharesOut = new BN(1); // Minimum 1 share
+
+      const start = Date.now();
+      const tx = await program.methods
+        .buyShares({ yes: {} }, amountIn, minSharesOut)
+        .accounts({
+          market: marketPDA,
+          config: configPDA,
+          pool: poolPDA,
+          vault: vaultPDA,
+          position: positionPDA,
+          priceHistory: priceHistoryPDA,
+          user: authority.publicKey,
+          systemProgram: SystemProgram.programId,
+        })
+        .rpc({ skipPreflight: true });
+
+      const duration = Date.now() - start;
+      console.log(`${duration}ms - Buy YES shares tx: ${tx}`);
+
+      // Verify position
+      const position = await program.account.position.fetch(positionPDA);
+      expect(position.yesShares.toNumber()).to.be.greaterThan(0);
+      expect(position.yesCost.toNumber()).to.equal(amountIn.toNumber());
+      expect(position.feesPaid.toNumber()).to.be.greaterThan(0);
+      console.log("YES shares bought:", position.yesShares.toNumber());
+    });
+
+    it("buys NO shares", async () => {
+      const amountIn = new BN(100_000_000); // 0.1 SOL
+      const minSharesOut = new BN(1);
+
+      const tx = await program.methods
+        .buyShares({ no: {} }, amountIn, minSharesOut)
+        .accounts({
+          market: marketPDA,
+          config: configPDA,
+          pool: poolPDA,
+          vault: vaultPDA,
+          position: positionPDA,
+          priceHistory: priceHistoryPDA,
+          user: authority.publicKey,
+          systemProgram: SystemProgram.programId,
+        })
+        .rpc({ skipPreflight: true });
+
+      console.log("Buy NO shares tx:", tx);
+
+      const position = await program.account.position.fetch(positionPDA);
+      expect(position.noShares.toNumber()).to.be.greaterThan(0);
+      console.log("NO shares bought:", position.noShares.toNumber());
+    });
+
+    it("buys an exact number of NO shares", async () => {
+      const sharesOut = new BN(10_000_000);
+      const maxAmountIn = new BN(20_000_000);
+
+      const positionBefore = await program.account.position.fetch(positionPDA);
+
+      const tx = await program.methods
+        .buySharesExactOut({ no: {} }, sharesOut, maxAmountIn)
+        .accounts({
+          market: marketPDA,
+          config: configPDA,
+          pool: poolPDA,
+          vault: vaultPDA,
+          position: positionPDA,
+          priceHistory: priceHistoryPDA,
+          user: authority.publicKey,
+          systemProgram: SystemProgram.programId,
+        })
+        .rpc({ skipPreflight: true });
+
+      console.log("Buy NO shares exact out tx:", tx);
+
+      const positionAfter = await program.account.position.fetch(positionPDA);
+      expect(positionAfter.noShares.toNumber()).to.equal(
+        positionBefore.noShares.toNumber() + sharesOut.toNumber()
+      );
+    });
+
+    it("sells YES shares", async () => {
+      const position = await program.account.position.fetch(positionPDA);
+      const sharesToSell = new BN(Math.floor(position.yesShares.toNumber() / 2)); // Sell half
+      const minAmountOut = new BN(1);
+
+      const start = Date.now();
+      const tx = await program.methods
+        .sellShares({ yes: {} }, sharesToSell, minAmountOut)
+        .accounts({
+          market: marketPDA,
+          config: configPDA,
+          pool: poolPDA,
+          vault: vaultPDA,
+          position: positionPDA,
+          priceHistory: priceHistoryPDA,
+          user: authority.publicKey,
+          systemProgram: SystemProgram.programId,
+        })
+        .rpc({ skipPreflight: true });
+
+      const duration = Date.now() - start;
+      console.log(`${duration}ms - Sell YES shares tx: ${tx}`);
+
+      const positionAfter = await program.account.position.fetch(positionPDA);
+      expect(positionAfter.yesShares.toNumber()).to.be.lessThan(position.yesShares.toNumber());
+      expect(positionAfter.yesProceeds.toNumber()).to.be.greaterThan(position.yesProceeds.toNumber());
+      expect(positionAfter.feesPaid.toNumber()).to.be.greaterThan(position.feesPaid.toNumber());
+      console.log("Realized P/L:", positionAfter.realizedPnl.toString());
+    });
+
+    it("emits a TradeExecuted event", async () => {
+      const amountIn = new BN(10_000_000);
+
+      const tx = await program.methods
+        .buyShares({ yes: {} }, amountIn, new BN(1))
+        .accounts({
+          market: marketPDA,
+          config: configPDA,
+          pool: poolPDA,
+          vault: vaultPDA,
+          position: positionPDA,
+          priceHistory: priceHistoryPDA,
+          user: authority.publicKey,
+          systemProgram: SystemProgram.programId,
+        })
+        .rpc({ commitment: "confirmed" });
+
+      // emit_cpi! events are carried in a self-invoked inner instruction
+      const txDetails = await provider.connection.getTransaction(tx, {
+        commitment: "confirmed",
+        maxSupportedTransactionVersion: 0,
+      });
+      const accountKeys = txDetails.transaction.message.staticAccountKeys;
+      const events = txDetails.meta.innerInstructions
+        .flatMap((inner) => inner.instructions)
+        .filter((ix) => accountKeys[ix.programIdIndex].equals(program.programId))
+        .map((ix) => {
+          const data = anchor.utils.bytes.bs58.decode(ix.data);
+          return program.coder.events.decode(
+            anchor.utils.bytes.base64.encode(Buffer.from(data.subarray(8)))
+          );
+        })
+        .filter((event) => event?.name === "tradeExecuted");
+
+      expect(events).to.have.lengthOf(1);
+      const trade = events[0].data;
+      expect(trade.amount.toNumber()).to.equal(amountIn.toNumber());
+      expect(trade.yesPriceAfter.toNumber()).to.be.greaterThan(trade.yesPriceBefore.toNumber());
+    });
+
+    it("records YES price candles", async () => {
+      const history = await program.account.priceHistory.fetch(priceHistoryPDA);
+      const pool = await program.account.pool.fetch(poolPDA);
+      expect(history.candles.length).to.be.greaterThan(0);
+
+      const latest = history.candles[history.head];
+      const yesPrice = Math.floor(
+        (pool.noReserve.toNumber() * 1_000_000) /
+          (pool.yesReserve.toNumber() + pool.noReserve.toNumber())
+      );
+      expect(latest.close.toNumber()).to.equal(yesPrice);
+      expect(latest.high.toNumber()).to.be.at.least(latest.low.toNumber());
+      expect(latest.volume.toNumber()).to.be.greaterThan(0);
+      console.log("Candles recorded:", history.candles.length);
+    });
+  });
+
+  describe("Limit Orders", () => {
+    it("places and cancels a sell limit order", async () => {
+      const orderId = new BN(1);
+      const [orderPDA] = PublicKey.findProgramAddressSync(
+        [
+          Buffer.from("limit_order"),
+          marketPDA.toBuffer(),
+          authority.publicKey.toBuffer(),
+          orderId.toArrayLike(Buffer, "le", 8),
+        ],
+        program.programId
+      );
+
+      const positionBefore = await program.account.position.fetch(positionPDA);
+      const size = new BN(Math.floor(positionBefore.yesShares.toNumber() / 4));
+
+      const tx = await program.methods
+        .placeLimitOrder(
+          orderId,
+          { yes: {} },
+          { sell: {} },
+          new BN(990_000), // Only fill at a 0.99 YES price
+          size,
+          expiration,
+          new BN(5_000)
+        )
+        .accounts({
+          market: marketPDA,
+          config: configPDA,
+          order: orderPDA,
+          position: positionPDA,
+          owner: authority.publicKey,
+          systemProgram: SystemProgram.programId,
+        })
+        .rpc({ skipPreflight: true });
+
+      console.log("Place Limit Order tx:", tx);
+
+      const order = await program.account.limitOrder.fetch(orderPDA);
+      expect(order.size.toNumber()).to.equal(size.toNumber());
+      const positionEscrowed = await program.account.position.fetch(positionPDA);
+      expect(positionEscrowed.yesShares.toNumber()).to.equal(
+        positionBefore.yesShares.toNumber() - size.toNumber()
+      );
+
+      await program.methods
+        .cancelLimitOrder()
+        .accounts({
+          market: marketPDA,
+          order: orderPDA,
+          position: positionPDA,
+          owner: authority.publicKey,
+        })
+        .rpc({ skipPreflight: true });
+
+      const positionAfter = await program.account.position.fetch(positionPDA);
+      expect(positionAfter.yesShares.toNumber()).to.equal(positionBefore.yesShares.toNumber());
+    });
+
+    it("rejects a fill whose execution price breaches the limit", async () => {
+      const orderId = new BN(2);
+      const [orderPDA] = PublicKey.findProgramAddressSync(
+        [
+          Buffer.from("limit_order"),
+          marketPDA.toBuffer(),
+          authority.publicKey.toBuffer(),
+          orderId.toArrayLike(Buffer, "le", 8),
+        ],
+        program.programId
+      );
+
+      // Limit one tick above spot: the spot price qualifies, but a large
+      // order's average execution price does not
+      const pool = await program.account.pool.fetch(poolPDA);
+      const spotYes = Math.floor(
+        (pool.noReserve.toNumber() * 1_000_000) /
+          (pool.yesReserve.toNumber() + pool.noReserve.toNumber())
+      );
+
+      await program.methods
+        .placeLimitOrder(
+          orderId,
+          { yes: {} },
+          { buy: {} },
+          new BN(spotYes + 1),
+          new BN(100_000_000),
+          expiration,
+          new BN(5_000)
+        )
+        .accounts({
+          market: marketPDA,
+          config: configPDA,
+          order: orderPDA,
+          position: positionPDA,
+          owner: authority.publicKey,
+          systemProgram: SystemProgram.programId,
+        })
+        .rpc();
+
+      try {
+        await program.methods
+          .fillLimitOrder()
+          .accounts({
+            market: marketPDA,
+            config: configPDA,
+            pool: poolPDA,
+            vault: vaultPDA,
+            order: orderPDA,
+            position: positionPDA,
+            priceHistory: priceHistoryPDA,
+            owner: authority.publicKey,
+            keeper: authority.publicKey,
+            systemProgram: SystemProgram.programId,
+          })
+          .rpc();
+        expect.fail("Should have thrown LimitPriceNotReached");
+      } catch (err: any) {
+        expect(err.toString()).to.include("LimitPriceNotReached");
+      }
+
+      await program.methods
+        .cancelLimitOrder()
+        .accounts({
+          market: marketPDA,
+          order: orderPDA,
+          position: positionPDA,
+          owner: authority.publicKey,
+        })
+        .rpc();
+    });
+  });
+
+  describe("Triggers", () => {
+    let pdas: Awaited<ReturnType<typeof setupMarket>>;
+
+    const triggerPDA = (triggerId: BN) =>
+      PublicKey.findProgramAddressSync(
+        [Buffer.from("trigger"), pdas.position.toBuffer(), triggerId.toArrayLike(Buffer, "le", 8)],
+        program.programId
+      )[0];
+
+    const createTrigger = async (triggerId: BN, kind: any, triggerPrice: BN, shares: BN) =>
+      program.methods
+        .createTrigger(triggerId, { yes: {} }, kind, triggerPrice, shares, new BN(1), new BN(5_000))
+        .accounts({
+          market: pdas.market,
+          config: configPDA,
+          position: pdas.position,
+          trigger: triggerPDA(triggerId),
+          owner: authority.publicKey,
+          systemProgram: SystemProgram.programId,
+        })
+        .rpc();
+
+    const executeTrigger = async (triggerId: BN) =>
+      program.methods
+        .executeTrigger()
+        .accounts({
+          market: pdas.market,
+          config: configPDA,
+          pool: pdas.pool,
+          vault: pdas.vault,
+          position: pdas.position,
+          trigger: triggerPDA(triggerId),
+          priceHistory: null,
+          owner: authority.publicKey,
+          keeper: authority.publicKey,
+          systemProgram: SystemProgram.programId,
+        })
+        .rpc();
+
+    before(async () => {
+      pdas = await setupMarket(20);
+      await buyIn(pdas, { yes: {} }, new BN(100_000_000));
+    });
+
+    it("does not fire a stop-loss above its trigger price", async () => {
+      const triggerId = new BN(1);
+      const position = await program.account.position.fetch(pdas.position);
+      await createTrigger(
+        triggerId,
+        { stopLoss: {} },
+        new BN(10_000), // 0.01 YES price
+        new BN(Math.floor(position.yesShares.toNumber() / 4))
+      );
+
+      try {
+        await executeTrigger(triggerId);
+        expect.fail("Should have thrown TriggerNotReached");
+      } catch (err: any) {
+        expect(err.toString()).to.include("TriggerNotReached");
+      }
+
+      await program.methods
+        .cancelTrigger()
+        .accounts({ trigger: triggerPDA(triggerId), owner: authority.publicKey })
+        .rpc();
+      expect(await provider.connection.getAccountInfo(triggerPDA(triggerId))).to.be.null;
+    });
+
+    it("sells the position once a take-profit fires", async () => {
+      const triggerId = new BN(2);
+      const positionBefore = await program.account.position.fetch(pdas.position);
+      const shares = new BN(Math.floor(positionBefore.yesShares.toNumber() / 4));
+      await createTrigger(triggerId, { takeProfit: {} }, new BN(10_000), shares);
+
+      await executeTrigger(triggerId);
+
+      const positionAfter = await program.account.position.fetch(pdas.position);
+      expect(positionAfter.yesShares.toNumber()).to.equal(
+        positionBefore.yesShares.toNumber() - shares.toNumber()
+      );
+      expect(positionAfter.yesProceeds.toNumber()).to.be.greaterThan(0);
+      expect(await provider.connection.getAccountInfo(triggerPDA(triggerId))).to.be.null;
+    });
+  });
+
+  describe("Order Book", () => {
+    let pdas: Awaited<ReturnType<typeof setupMarket>>;
+    let orderBookPDA: PublicKey;
+    const size = new BN(20_000_000);
+
+    const placeBid = async (price: BN) =>
+      program.methods
+        .placeBookOrder({ bid: {} }, price, size)
+        .accounts({
+          market: pdas.market,
+          config: configPDA,
+          orderBook: orderBookPDA,
+          position: pdas.position,
+          owner: authority.publicKey,
+          systemProgram: SystemProgram.programId,
+        })
+        .rpc();
+
+    const manageAccounts = () => ({
+      market: pdas.market,
+      orderBook: orderBookPDA,
+      position: pdas.position,
+      owner: authority.publicKey,
+    });
+
+    before(async () => {
+      pdas = await setupMarket(30);
+      [orderBookPDA] = PublicKey.findProgramAddressSync(
+        [Buffer.from("order_book"), pdas.market.toBuffer()],
+        program.programId
+      );
+      await program.methods
+        .initializeOrderBook()
+        .accounts({
+          market: pdas.market,
+          orderBook: orderBookPDA,
+          payer: authority.publicKey,
+          systemProgram: SystemProgram.programId,
+        })
+        .rpc();
+      await buyIn(pdas, { yes: {} }, new BN(200_000_000));
+    });
+
+    it("queues bids by price, then by time", async () => {
+      await placeBid(new BN(900_000)); // order 0
+      await placeBid(new BN(950_000)); // order 1
+      await placeBid(new BN(950_000)); // order 2
+
+      const book = await program.account.orderBook.fetch(orderBookPDA);
+      expect(book.bids.map((bid: any) => bid.orderId.toNumber())).to.deep.equal([1, 2, 0]);
+    });
+
+    it("rejects new book orders while the protocol is paused", async () => {
+      const setPaused = (paused: boolean) =>
+        program.methods
+          .setPaused(paused)
+          .accounts({ config: configPDA, guardian: authority.publicKey })
+          .rpc();
+
+      await setPaused(true);
+      try {
+        await placeBid(new BN(500_000));
+        expect.fail("Should have thrown ProtocolPaused");
+      } catch (err: any) {
+        expect(err.toString()).to.include("ProtocolPaused");
+      } finally {
+        await setPaused(false);
+      }
+    });
+
+    it("rejects an ask that would cross the best bid", async () => {
+      try {
+        await program.methods
+          .placeBookOrder({ ask: {} }, new BN(940_000), size)
+          .accounts({
+            market: pdas.market,
+            config: configPDA,
+            orderBook: orderBookPDA,
+            position: pdas.position,
+            owner: authority.publicKey,
+            systemProgram: SystemProgram.programId,
+          })
+          .rpc();
+        expect.fail("Should have thrown OrderWouldCross");
+      } catch (err: any) {
+        expect(err.toString()).to.include("OrderWouldCross");
+      }
+    });
+
+    it("fills a routed sell from the best bid first", async () => {
+      const half = size.divn(2);
+      await program.methods
+        .sellSharesRouted({ yes: {} }, size.add(half), new BN(1))
+        .accounts({
+          market: pdas.market,
+          config: configPDA,
+          pool: pdas.pool,
+          vault: pdas.vault,
+          orderBook: orderBookPDA,
+          position: pdas.position,
+          priceHistory: null,
+          user: authority.publicKey,
+          systemProgram: SystemProgram.programId,
+        })
+        .rpc();
+
+      const book = await program.account.orderBook.fetch(orderBookPDA);
+      const [first, second, third] = book.bids;
+      expect(first.orderId.toNumber()).to.equal(1);
+      expect(first.remaining.toNumber()).to.equal(0);
+      expect(first.filled.toNumber()).to.equal(size.toNumber());
+      expect(second.orderId.toNumber()).to.equal(2);
+      expect(second.remaining.toNumber()).to.equal(half.toNumber());
+      expect(third.orderId.toNumber()).to.equal(0);
+      expect(third.remaining.toNumber()).to.equal(size.toNumber());
+    });
+
+    it("settles a filled bid into the position and frees its slot", async () => {
+      const positionBefore = await program.account.position.fetch(pdas.position);
+
+      await program.methods.settleBookOrder(new BN(1)).accounts(manageAccounts()).rpc();
+
+      const positionAfter = await program.account.position.fetch(pdas.position);
+      expect(positionAfter.yesShares.toNumber()).to.equal(
+        positionBefore.yesShares.toNumber() + size.toNumber()
+      );
+      const book = await program.account.orderBook.fetch(orderBookPDA);
+      expect(book.bids.map((bid: any) => bid.orderId.toNumber())).to.deep.equal([2, 0]);
+    });
+
+    it("cancels a partly filled bid, settling its fills", async () => {
+      const positionBefore = await program.account.position.fetch(pdas.position);
+
+      await program.methods.cancelBookOrder(new BN(2)).accounts(manageAccounts()).rpc();
+
+      const positionAfter = await program.account.position.fetch(pdas.position);
+      expect(positionAfter.yesShares.toNumber()).to.equal(
+        positionBefore.yesShares.toNumber() + size.divn(2).toNumber()
+      );
+      const book = await program.account.orderBook.fetch(orderBookPDA);
+      expect(book.bids.map((bid: any) => bid.orderId.toNumber())).to.deep.equal([0]);
+    });
+  });
+
+  describe("Batch Auctions", () => {
+    let pdas: Awaited<ReturnType<typeof setupMarket>>;
+    let batchPDA: PublicKey;
+
+    const submit = async (side: any, direction: any, amount: BN) =>
+      program.methods
+        .submitBatchOrder(side, direction, amount, new BN(1))
+        .accounts({
+          market: pdas.market,
+          config: configPDA,
+          batchAuction: batchPDA,
+          position: pdas.position,
+          owner: authority.publicKey,
+          systemProgram: SystemProgram.programId,
+        })
+        .rpc();
+
+    const cancel = async (orderId: number) =>
+      program.methods
+        .cancelBatchOrder(new BN(orderId))
+        .accounts({
+          market: pdas.market,
+          batchAuction: batchPDA,
+          position: pdas.position,
+          owner: authority.publicKey,
+        })
+        .rpc();
+
+    before(async () => {
+      pdas = await setupMarket(31);
+      [batchPDA] = PublicKey.findProgramAddressSync(
+        [Buffer.from("batch_auction"), pdas.market.toBuffer()],
+        program.programId
+      );
+      await buyIn(pdas, { yes: {} }, new BN(50_000_000));
+      await program.methods
+        .enableBatchAuction(new BN(1))
+        .accounts({
+          market: pdas.market,
+          batchAuction: batchPDA,
+          authority: authority.publicKey,
+          systemProgram: SystemProgram.programId,
+        })
+        .rpc();
+    });
+
+    it("returns escrow when a queued order is cancelled", async () => {
+      const sharesBefore = (await program.account.position.fetch(pdas.position)).yesShares;
+      await submit({ yes: {} }, { sell: {} }, new BN(10_000_000)); // order 0
+      expect((await program.account.position.fetch(pdas.position)).yesShares.toString()).to.equal(
+        sharesBefore.subn(10_000_000).toString()
+      );
+
+      await cancel(0);
+
+      expect((await program.account.position.fetch(pdas.position)).yesShares.toString()).to.equal(
+        sharesBefore.toString()
+      );
+      expect((await program.account.batchAuction.fetch(batchPDA)).orders).to.have.length(0);
+    });
+
+    it("blocks cancelling the market while orders are queued", async () => {
+      await submit({ no: {} }, { buy: {} }, new BN(5_000_000)); // order 1
+      try {
+        await program.methods
+          .cancelMarket()
+          .accounts({
+            market: pdas.market,
+            parentMarket: null,
+            batchAuction: batchPDA,
+            authority: authority.publicKey,
+          })
+          .rpc();
+        expect.fail("Should have thrown BatchNotEmpty");
+      } catch (err: any) {
+        expect(err.toString()).to.include("BatchNotEmpty");
+      }
+      await cancel(1);
+    });
+
+    it("clears opposing orders at one uniform price", async () => {
+      const buyYes = new BN(40_000_000);
+      const buyNo = new BN(20_000_000);
+      const sellYes = new BN(10_000_000);
+      await submit({ yes: {} }, { buy: {} }, buyYes); // order 2
+      await submit({ no: {} }, { buy: {} }, buyNo); // order 3
+      await submit({ yes: {} }, { sell: {} }, sellYes); // order 4
+
+      const market = await program.account.market.fetch(pdas.market);
+      const pool = await program.account.pool.fetch(pdas.pool);
+      const positionBefore = await program.account.position.fetch(pdas.position);
+      const feeBps = market.params.lpFeeBps.add(market.protocolFeeBps).add(market.creatorFeeBps);
+      const afterFee = (amount: BN) => amount.sub(amount.mul(feeBps).divn(10_000));
+
+      // YES buys pay into the NO reserve; NO buys and YES sells into the YES reserve
+      const yesSide = pool.yesReserve.add(afterFee(buyNo)).add(sellYes);
+      const noSide = pool.noReserve.add(afterFee(buyYes));
+      const yesOut = afterFee(buyYes).mul(yesSide).div(noSide);
+      const noOut = afterFee(buyNo).mul(noSide).div(yesSide);
+      const sellGross = sellYes.mul(noSide).div(yesSide);
+
+      await new Promise((resolve) => setTimeout(resolve, 1500));
+      const pair = { pubkey: pdas.position, isWritable: true, isSigner: false };
+      const owner = { pubkey: authority.publicKey, isWritable: true, isSigner: false };
+      await program.methods
+        .clearBatch()
+        .accounts({
+          market: pdas.market,
+          config: configPDA,
+          pool: pdas.pool,
+          vault: pdas.vault,
+          batchAuction: batchPDA,
+          priceHistory: null,
+          cranker: authority.publicKey,
+          systemProgram: SystemProgram.programId,
+        })
+        .remainingAccounts([pair, owner, pair, owner, pair, owner])
+        .rpc();
+
+      const positionAfter = await program.account.position.fetch(pdas.position);
+      expect(positionAfter.yesShares.sub(positionBefore.yesShares).toString()).to.equal(
+        yesOut.toString()
+      );
+      expect(positionAfter.noShares.sub(positionBefore.noShares).toString()).to.equal(
+        noOut.toString()
+      );
+      expect(positionAfter.yesProceeds.sub(positionBefore.yesProceeds).toString()).to.equal(
+        sellGross.sub(sellGross.mul(feeBps).divn(10_000)).toString()
+      );
+      const fee = (amount: BN) => amount.mul(feeBps).divn(10_000);
+      expect(positionAfter.feesPaid.sub(positionBefore.feesPaid).toString()).to.equal(
+        fee(buyYes).add(fee(buyNo)).add(fee(sellGross)).toString()
+      );
+
+      // Only the net imbalance moved the pool
+      const poolAfter = await program.account.pool.fetch(pdas.pool);
+      expect(poolAfter.yesReserve.toString()).to.equal(yesSide.sub(yesOut).toString());
+      expect(poolAfter.noReserve.toString()).to.equal(noSide.sub(noOut).sub(sellGross).toString());
+      expect((await program.account.batchAuction.fetch(batchPDA)).orders).to.have.length(0);
+    });
+  });
+
+  describe("RFQ", () => {
+    let pdas: Awaited<ReturnType<typeof setupMarket>>;
+    const maker = Keypair.generate();
+    let rfqMakerPDA: PublicKey;
+    let makerPositionPDA: PublicKey;
+
+    const rfqTrade = async (expiry: BN) =>
+      program.methods
+        .rfqTrade({ yes: {} }, new BN(10_000_000), new BN(600_000), expiry)
+        .accounts({
+          market: pdas.market,
+          config: configPDA,
+          pool: pdas.pool,
+          vault: pdas.vault,
+          rfqMaker: rfqMakerPDA,
+          takerPosition: pdas.position,
+          makerPosition: makerPositionPDA,
+          taker: authority.publicKey,
+          maker: maker.publicKey,
+          systemProgram: SystemProgram.programId,
+        })
+        .signers([maker])
+        .rpc({ commitment: "confirmed" });
+
+    before(async () => {
+      pdas = await setupMarket(32);
+      [rfqMakerPDA] = PublicKey.findProgramAddressSync(
+        [Buffer.from("rfq_maker"), pdas.market.toBuffer(), maker.publicKey.toBuffer()],
+        program.programId
+      );
+      [makerPositionPDA] = PublicKey.findProgramAddressSync(
+        [Buffer.from("position"), pdas.market.toBuffer(), maker.publicKey.toBuffer()],
+        program.programId
+      );
+      await provider.connection.confirmTransaction(
+        await provider.connection.requestAirdrop(maker.publicKey, LAMPORTS_PER_SOL)
+      );
+      await program.methods
+        .approveRfqMaker()
+        .accounts({
+          market: pdas.market,
+          rfqMaker: rfqMakerPDA,
+          maker: maker.publicKey,
+          authority: authority.publicKey,
+          systemProgram: SystemProgram.programId,
+        })
+        .rpc();
+    });
+
+    it("rejects an expired quote", async () => {
+      try {
+        await rfqTrade(new BN(Math.floor(Date.now() / 1000) - 60));
+        expect.fail("Should have thrown QuoteExpired");
+      } catch (err: any) {
+        expect(err.toString()).to.include("QuoteExpired");
+      }
+    });
+
+    it("mints complete sets at the maker's quoted price", async () => {
+      await rfqTrade(new BN(Math.floor(Date.now() / 1000) + 60));
+
+      const taker = await program.account.position.fetch(pdas.position);
+      const makerPosition = await program.account.position.fetch(makerPositionPDA);
+      expect(taker.yesShares.toNumber()).to.equal(10_000_000);
+      expect(taker.yesCost.toNumber()).to.equal(6_000_000);
+      expect(makerPosition.noShares.toNumber()).to.equal(10_000_000);
+      expect(makerPosition.noCost.toNumber()).to.equal(4_000_000);
+    });
+
+    it("reports the taker's side as a TradeExecuted at unchanged reserves", async () => {
+      const tx = await rfqTrade(new BN(Math.floor(Date.now() / 1000) + 60));
+
+      const txDetails = await provider.connection.getTransaction(tx, {
+        commitment: "confirmed",
+        maxSupportedTransactionVersion: 0,
+      });
+      const accountKeys = txDetails.transaction.message.staticAccountKeys;
+      const events = txDetails.meta.innerInstructions
+        .flatMap((inner) => inner.instructions)
+        .filter((ix) => accountKeys[ix.programIdIndex].equals(program.programId))
+        .map((ix) => {
+          const data = anchor.utils.bytes.bs58.decode(ix.data);
+          return program.coder.events.decode(
+            anchor.utils.bytes.base64.encode(Buffer.from(data.subarray(8)))
+          );
+        })
+        .filter((event) => event?.name === "tradeExecuted");
+
+      expect(events).to.have.lengthOf(1);
+      const trade = events[0].data;
+      expect(trade.amount.toNumber()).to.equal(6_000_000);
+      expect(trade.shares.toNumber()).to.equal(10_000_000);
+      expect(trade.yesReserveAfter.toString()).to.equal(trade.yesReserveBefore.toString());
+    });
+  });
+
+  describe("LP Fees", () => {
+    let pdas: Awaited<ReturnType<typeof setupMarket>>;
+    const lateLp = Keypair.generate();
+
+    const lpPositionPDA = (user: PublicKey) =>
+      PublicKey.findProgramAddressSync(
+        [Buffer.from("lp_position"), pdas.pool.toBuffer(), user.toBuffer()],
+        program.programId
+      )[0];
+
+    const claimLpFees = async (user: Keypair | null) =>
+      program.methods
+        .claimLpFees()
+        .accounts({
+          market: pdas.market,
+          pool: pdas.pool,
+          vault: pdas.vault,
+          lpPosition: lpPositionPDA(user ? user.publicKey : authority.publicKey),
+          user: user ? user.publicKey : authority.publicKey,
+          systemProgram: SystemProgram.programId,
+        })
+        .signers(user ? [user] : [])
+        .rpc();
+
+    before(async () => {
+      pdas = await setupMarket(35);
+      await provider.connection.confirmTransaction(
+        await provider.connection.requestAirdrop(lateLp.publicKey, LAMPORTS_PER_SOL)
+      );
+    });
+
+    it("pays the LP share of trading fees to the pool's LPs", async () => {
+      const amountIn = new BN(100_000_000);
+      await buyIn(pdas, { yes: {} }, amountIn);
+
+      const market = await program.account.market.fetch(pdas.market);
+      const pool = await program.account.pool.fetch(pdas.pool);
+      const feeBps = market.params.lpFeeBps.add(market.protocolFeeBps).add(market.creatorFeeBps);
+      const fee = amountIn.mul(feeBps).divn(10_000);
+      const lpFee = fee
+        .sub(fee.mul(market.protocolFeeBps).div(feeBps))
+        .sub(fee.mul(market.creatorFeeBps).div(feeBps));
+      const precision = new BN("1000000000000");
+      const growth = lpFee.mul(precision).div(pool.lpTokenSupply);
+      expect(pool.feeGrowthPerLp.toString()).to.equal(growth.toString());
+
+      const lpPosition = await program.account.lpPosition.fetch(lpPositionPDA(authority.publicKey));
+      const owed = lpPosition.lpTokens.mul(growth).div(precision);
+      const vaultBefore = await provider.connection.getBalance(pdas.vault);
+
+      await claimLpFees(null);
+
+      expect(vaultBefore - (await provider.connection.getBalance(pdas.vault))).to.equal(
+        owed.toNumber()
+      );
+      const claimed = await program.account.lpPosition.fetch(lpPositionPDA(authority.publicKey));
+      expect(claimed.feesOwed.toNumber()).to.equal(0);
+      expect(claimed.feeGrowthCheckpoint.toString()).to.equal(growth.toString());
+    });
+
+    it("does not pay a new LP for fees earned before it joined", async () => {
+      await program.methods
+        .addLiquidity(new BN(200_000_000), new BN(1))
+        .accounts({
+          market: pdas.market,
+          config: configPDA,
+          pool: pdas.pool,
+          vault: pdas.vault,
+          lpPosition: lpPositionPDA(lateLp.publicKey),
+          user: lateLp.publicKey,
+          systemProgram: SystemProgram.programId,
+        })
+        .signers([lateLp])
+        .rpc();
+
+      try {
+        await claimLpFees(lateLp);
+        expect.fail("Should have thrown NoFeesToClaim");
+      } catch (err: any) {
+        expect(err.toString()).to.include("NoFeesToClaim");
+      }
+    });
+
+    it("refuses a second claim with nothing new accrued", async () => {
+      try {
+        await claimLpFees(null);
+        expect.fail("Should have thrown NoFeesToClaim");
+      } catch (err: any) {
+        expect(err.toString()).to.include("NoFeesToClaim");
+      }
+    });
+  });
+
+  describe("Market Roles", () => {
+    let pdas: Awaited<ReturnType<typeof setupMarket>>;
+    const pauser = Keypair.generate();
+    const resolver = Keypair.generate();
+
+    const haltAction = (method: "haltMarket" | "resumeMarket", signer: Keypair | null) =>
+      program.methods[method]()
+        .accounts({
+          config: configPDA,
+          market: pdas.market,
+          signer: signer ? signer.publicKey : authority.publicKey,
+        })
+        .signers(signer ? [signer] : [])
+        .rpc();
+
+    before(async () => {
+      pdas = await setupMarket(39);
+    });
+
+    it("lets only the authority assign roles", async () => {
+      try {
+        await program.methods
+          .setMarketRoles(resolver.publicKey, authority.publicKey, pauser.publicKey)
+          .accounts({ market: pdas.market, authority: pauser.publicKey })
+          .signers([pauser])
+          .rpc();
+        expect.fail("Should have thrown Unauthorized");
+      } catch (err: any) {
+        expect(err.toString()).to.include("Unauthorized");
+      }
+
+      await program.methods
+        .setMarketRoles(resolver.publicKey, authority.publicKey, pauser.publicKey)
+        .accounts({ market: pdas.market, authority: authority.publicKey })
+        .rpc();
+      const market = await program.account.market.fetch(pdas.market);
+      expect(market.resolver.toBase58()).to.equal(resolver.publicKey.toBase58());
+      expect(market.feeCollector.toBase58()).to.equal(authority.publicKey.toBase58());
+      expect(market.pauser.toBase58()).to.equal(pauser.publicKey.toBase58());
+    });
+
+    it("lets the pauser lift its own halt", async () => {
+      await haltAction("haltMarket", pauser);
+      let market = await program.account.market.fetch(pdas.market);
+      expect(market.status).to.deep.equal({ halted: {} });
+      expect(market.haltedBy).to.deep.equal({ pauser: {} });
+
+      await haltAction("resumeMarket", pauser);
+      market = await program.account.market.fetch(pdas.market);
+      expect(market.status).to.deep.equal({ active: {} });
+      expect(market.haltedBy).to.be.null;
+    });
+
+    it("keeps a guardian halt in place until the guardian lifts it", async () => {
+      await haltAction("haltMarket", null);
+      expect((await program.account.market.fetch(pdas.market)).haltedBy).to.deep.equal({
+        guardian: {},
+      });
+
+      try {
+        await haltAction("resumeMarket", pauser);
+        expect.fail("Should have thrown Unauthorized");
+      } catch (err: any) {
+        expect(err.toString()).to.include("Unauthorized");
+      }
+
+      await haltAction("resumeMarket", null);
+      expect((await program.account.market.fetch(pdas.market)).status).to.deep.equal({
+        active: {},
+      });
+    });
+
+    it("transfers the market authority in two steps", async () => {
+      const newAuthority = Keypair.generate();
+      await program.methods
+        .transferMarketAuthority(newAuthority.publicKey)
+        .accounts({ market: pdas.market, authority: authority.publicKey })
+        .rpc();
+      let market = await program.account.market.fetch(pdas.market);
+      expect(market.authority.toBase58()).to.equal(authority.publicKey.toBase58());
+      expect(market.pendingAuthority.toBase58()).to.equal(newAuthority.publicKey.toBase58());
+
+      try {
+        await program.methods
+          .acceptMarketAuthority()
+          .accounts({ market: pdas.market, authority: pauser.publicKey })
+          .signers([pauser])
+          .rpc();
+        expect.fail("Should have thrown Unauthorized");
+      } catch (err: any) {
+        expect(err.toString()).to.include("Unauthorized");
+      }
+
+      await program.methods
+        .acceptMarketAuthority()
+        .accounts({ market: pdas.market, authority: newAuthority.publicKey })
+        .signers([newAuthority])
+        .rpc();
+      market = await program.account.market.fetch(pdas.market);
+      expect(market.authority.toBase58()).to.equal(newAuthority.publicKey.toBase58());
+      expect(market.pendingAuthority).to.be.null;
+    });
+  });
+
+  describe("Market Metadata", () => {
+    let pdas: Awaited<ReturnType<typeof setupMarket>>;
+    let metadata: PublicKey;
+
+    const args = (title: string) => ({
+      title,
+      category: "crypto",
+      tags: ["btc", "daily"],
+      resolutionSource: "Pyth BTC/USD",
+      rulesUri: "https://example.com/rules",
+      imageUri: "https://example.com/image.png",
+    });
+
+    before(async () => {
+      pdas = await setupMarket(42);
+      [metadata] = PublicKey.findProgramAddressSync(
+        [Buffer.from("metadata"), pdas.market.toBuffer()],
+        program.programId
+      );
+    });
+
+    it("creates and resizes metadata before trading", async () => {
+      await program.methods
+        .createMarketMetadata(args("Short"))
+        .accounts({
+          market: pdas.market,
+          metadata,
+          authority: authority.publicKey,
+          systemProgram: SystemProgram.programId,
+        })
+        .rpc();
+
+      const longTitle = "A much longer title that forces the account to grow";
+      await program.methods
+        .updateMarketMetadata(args(longTitle))
         .accounts({
-          market: marketPDA,
-          pythPriceAccount: mockPythPriceAccount.publicKey,
+          market: pdas.market,
+          metadata,
           authority: authority.publicKey,
           systemProgram: SystemProgram.programId,
         })
-        .rpc({ skipPreflight: true });
+        .rpc();
 
-      const duration = Date.now() - start;
-      console.log(`${duration}ms - Create Market tx: ${tx}`);
+      const stored = await program.account.marketMetadata.fetch(metadata);
+      expect(stored.title).to.equal(longTitle);
+      expect(stored.tags).to.deep.equal(["btc", "daily"]);
+      const market = await program.account.market.fetch(pdas.market);
+      expect(market.metadata.toBase58()).to.equal(metadata.toBase58());
+    });
 
-      // Verify market state
-      const market = await program.account.market.fetch(marketPDA);
-      expect(market.authority.toBase58()).to.equal(authority.publicKey.toBase58());
-      expect(market.strikePrice.toNumber()).to.equal(strikePrice.toNumber());
-      expect(market.description).to.equal(description);
-      expect(market.status).to.deep.equal({ active: {} });
-      expect(market.pythPriceAccount.toBase58()).to.equal(mockPythPriceAccount.publicKey.toBase58());
+    it("stays frozen after every share is sold back", async () => {
+      await buyIn(pdas, { yes: {} }, new BN(10_000_000));
+      const position = await program.account.position.fetch(pdas.position);
+      await program.methods
+        .sellShares({ yes: {} }, position.yesShares, new BN(0))
+        .accounts({
+          market: pdas.market,
+          config: configPDA,
+          pool: pdas.pool,
+          vault: pdas.vault,
+          position: pdas.position,
+          priceHistory: null,
+          user: authority.publicKey,
+          systemProgram: SystemProgram.programId,
+        })
+        .rpc();
+      const market = await program.account.market.fetch(pdas.market);
+      expect(market.totalYesShares.toNumber()).to.equal(0);
+
+      try {
+        await program.methods
+          .updateMarketMetadata(args("Changed after trading"))
+          .accounts({
+            market: pdas.market,
+            metadata,
+            authority: authority.publicKey,
+            systemProgram: SystemProgram.programId,
+          })
+          .rpc();
+        expect.fail("Should have thrown MetadataFrozen");
+      } catch (err: any) {
+        expect(err.toString()).to.include("MetadataFrozen");
+      }
     });
   });
 
-  describe("Liquidity Pool", () => {
-    it("initializes the liquidity pool", async () => {
-      const initialLiquidity = new BN(1_000_000_000); // 1 SOL
+  describe("Claims", () => {
+    let pdas: Awaited<ReturnType<typeof setupMarket>>;
 
-      const start = Date.now();
-      const tx = await program.methods
-        .initializePool(initialLiquidity)
+    before(async () => {
+      pdas = await setupMarket(44);
+      await buyIn(pdas, { yes: {} }, new BN(10_000_000));
+    });
+
+    it("rejects cranking claims before resolution and leaves positions open", async () => {
+      try {
+        await program.methods
+          .crankClaims(true)
+          .accounts({
+            market: pdas.market,
+            vault: pdas.vault,
+            cranker: authority.publicKey,
+            systemProgram: SystemProgram.programId,
+          })
+          .remainingAccounts([
+            { pubkey: pdas.position, isSigner: false, isWritable: true },
+            { pubkey: authority.publicKey, isSigner: false, isWritable: true },
+          ])
+          .rpc();
+        expect.fail("Should have thrown MarketNotResolved");
+      } catch (err: any) {
+        expect(err.toString()).to.include("MarketNotResolved");
+      }
+      const position = await program.account.position.fetch(pdas.position);
+      expect(position.yesShares.toNumber()).to.be.greaterThan(0);
+    });
+
+    it("skips unresolved markets in claim_many", async () => {
+      const before = await program.account.position.fetch(pdas.position);
+      await program.methods
+        .claimMany()
+        .accounts({ user: authority.publicKey, systemProgram: SystemProgram.programId })
+        .remainingAccounts([
+          { pubkey: pdas.market, isSigner: false, isWritable: true },
+          { pubkey: pdas.vault, isSigner: false, isWritable: true },
+          { pubkey: pdas.position, isSigner: false, isWritable: true },
+        ])
+        .rpc();
+      const after = await program.account.position.fetch(pdas.position);
+      expect(after.claimedShares.toString()).to.equal(before.claimedShares.toString());
+      expect(after.yesShares.toString()).to.equal(before.yesShares.toString());
+    });
+
+    it("caps the number of markets in claim_many", async () => {
+      const triple = [
+        { pubkey: pdas.market, isSigner: false, isWritable: true },
+        { pubkey: pdas.vault, isSigner: false, isWritable: true },
+        { pubkey: pdas.position, isSigner: false, isWritable: true },
+      ];
+      try {
+        await program.methods
+          .claimMany()
+          .accounts({ user: authority.publicKey, systemProgram: SystemProgram.programId })
+          .remainingAccounts(Array(21).fill(triple).flat())
+          .rpc();
+        expect.fail("Should have thrown TooManyClaims");
+      } catch (err: any) {
+        expect(err.toString()).to.include("TooManyClaims");
+      }
+    });
+  });
+
+  describe("Share Transfers", () => {
+    let pdas: Awaited<ReturnType<typeof setupMarket>>;
+    const recipient = Keypair.generate();
+    let recipientPosition: PublicKey;
+
+    const transfer = (amount: BN) =>
+      program.methods
+        .transferShares({ yes: {} }, amount, recipient.publicKey)
         .accounts({
-          market: marketPDA,
-          pool: poolPDA,
-          vault: vaultPDA,
-          authority: authority.publicKey,
+          market: pdas.market,
+          config: configPDA,
+          fromPosition: pdas.position,
+          toPosition: recipientPosition,
+          user: authority.publicKey,
           systemProgram: SystemProgram.programId,
         })
-        .rpc({ skipPreflight: true });
+        .rpc();
 
-      const duration = Date.now() - start;
-      console.log(`${duration}ms - Initialize Pool tx: ${tx}`);
+    before(async () => {
+      pdas = await setupMarket(47);
+      [recipientPosition] = PublicKey.findProgramAddressSync(
+        [Buffer.from("position"), pdas.market.toBuffer(), recipient.publicKey.toBuffer()],
+        program.programId
+      );
+      await provider.connection.confirmTransaction(
+        await provider.connection.requestAirdrop(recipient.publicKey, LAMPORTS_PER_SOL)
+      );
 
-      // Verify pool state
-      const pool = await program.account.pool.fetch(poolPDA);
-      expect(pool.market.toBase58()).to.equal(marketPDA.toBase58());
-      expect(pool.yesReserve.toNumber()).to.equal(initialLiquidity.toNumber());
-      expect(pool.noReserve.toNumber()).to.equal(initialLiquidity.toNumber());
-      expect(pool.totalLiquidity.toNumber()).to.equal(initialLiquidity.toNumber() * 2);
+      // The recipient buys first, so the sender's later shares cost more
+      await program.methods
+        .buyShares({ yes: {} }, new BN(20_000_000), new BN(1))
+        .accounts({
+          market: pdas.market,
+          config: configPDA,
+          pool: pdas.pool,
+          vault: pdas.vault,
+          position: recipientPosition,
+          priceHistory: null,
+          user: recipient.publicKey,
+          systemProgram: SystemProgram.programId,
+        })
+        .signers([recipient])
+        .rpc();
+      await buyIn(pdas, { yes: {} }, new BN(50_000_000));
     });
 
-    it("allows adding more liquidity", async () => {
-      const addAmount = new BN(500_000_000); // 0.5 SOL
+    it("moves shares and blends the recipient's average price", async () => {
+      const senderBefore = await program.account.position.fetch(pdas.position);
+      const recipientBefore = await program.account.position.fetch(recipientPosition);
+      const amount = senderBefore.yesShares.divn(2);
 
-      const poolBefore = await program.account.pool.fetch(poolPDA);
+      await transfer(amount);
 
-      const tx = await program.methods
-        .addLiquidity(addAmount)
+      const sender = await program.account.position.fetch(pdas.position);
+      const received = await program.account.position.fetch(recipientPosition);
+      expect(sender.yesShares.toString()).to.equal(senderBefore.yesShares.sub(amount).toString());
+      expect(sender.yesAvgPrice.toString()).to.equal(senderBefore.yesAvgPrice.toString());
+      expect(received.yesShares.toString()).to.equal(
+        recipientBefore.yesShares.add(amount).toString()
+      );
+
+      const movedCost = amount.mul(senderBefore.yesAvgPrice).divn(1_000_000);
+      const expectedAvg = recipientBefore.yesAvgPrice
+        .mul(recipientBefore.yesShares)
+        .add(movedCost.muln(1_000_000))
+        .div(received.yesShares);
+      expect(received.yesAvgPrice.toString()).to.equal(expectedAvg.toString());
+      expect(received.yesAvgPrice.gt(recipientBefore.yesAvgPrice)).to.be.true;
+
+      // The cost basis moves with the shares rather than being counted twice
+      expect(sender.yesCost.toString()).to.equal(senderBefore.yesCost.sub(movedCost).toString());
+      expect(received.yesCost.toString()).to.equal(recipientBefore.yesCost.add(movedCost).toString());
+    });
+
+    it("rejects transfers while the protocol is paused", async () => {
+      const setPaused = (paused: boolean) =>
+        program.methods
+          .setPaused(paused)
+          .accounts({ config: configPDA, guardian: authority.publicKey })
+          .rpc();
+
+      await setPaused(true);
+      try {
+        await transfer(new BN(1_000));
+        expect.fail("Should have thrown ProtocolPaused");
+      } catch (err: any) {
+        expect(err.toString()).to.include("ProtocolPaused");
+      } finally {
+        await setPaused(false);
+      }
+    });
+  });
+
+  describe("Price History", () => {
+    let pdas: Awaited<ReturnType<typeof setupMarket>>;
+    let history: PublicKey;
+
+    const yesPrice = async () => {
+      const pool = await program.account.pool.fetch(pdas.pool);
+      return pool.noReserve
+        .muln(1_000_000)
+        .div(pool.yesReserve.add(pool.noReserve))
+        .toNumber();
+    };
+
+    const tradeWithHistory = (side: any, amountIn: BN) =>
+      program.methods
+        .buyShares(side, amountIn, new BN(1))
         .accounts({
-          market: marketPDA,
-          pool: poolPDA,
-          vault: vaultPDA,
+          market: pdas.market,
+          config: configPDA,
+          pool: pdas.pool,
+          vault: pdas.vault,
+          position: pdas.position,
+          priceHistory: history,
           user: authority.publicKey,
           systemProgram: SystemProgram.programId,
         })
-        .rpc({ skipPreflight: true });
+        .rpc();
 
-      console.log("Add Liquidity tx:", tx);
-
-      const poolAfter = await program.account.pool.fetch(poolPDA);
-      expect(poolAfter.totalLiquidity.toNumber()).to.be.greaterThan(
-        poolBefore.totalLiquidity.toNumber()
+    before(async () => {
+      pdas = await setupMarket(50);
+      [history] = PublicKey.findProgramAddressSync(
+        [Buffer.from("price_history"), pdas.market.toBuffer()],
+        program.programId
       );
     });
-  });
 
-  describe("Trading (AMM)", () => {
-    it("buys YES shares", async () => {
-      const amountIn = new BN(100_000_000); // 0.1 SOL
-      const minSharesOut = new BN(1); // Minimum 1 share
+    it("lets only the market authority start a history", async () => {
+      const stranger = Keypair.generate();
+      await provider.connection.confirmTransaction(
+        await provider.connection.requestAirdrop(stranger.publicKey, LAMPORTS_PER_SOL)
+      );
+      try {
+        await program.methods
+          .initializePriceHistory(new BN(86_400))
+          .accounts({
+            market: pdas.market,
+            priceHistory: history,
+            authority: stranger.publicKey,
+            systemProgram: SystemProgram.programId,
+          })
+          .signers([stranger])
+          .rpc();
+        expect.fail("Should have thrown Unauthorized");
+      } catch (err: any) {
+        expect(err.toString()).to.include("Unauthorized");
+      }
 
-      const start = Date.now();
-      const tx = await program.methods
-        .buyShares({ yes: {} }, amountIn, minSharesOut)
+      await program.methods
+        .initializePriceHistory(new BN(86_400))
         .accounts({
-          market: marketPDA,
-          pool: poolPDA,
-          vault: vaultPDA,
-          position: positionPDA,
-          user: authority.publicKey,
+          market: pdas.market,
+          priceHistory: history,
+          authority: authority.publicKey,
           systemProgram: SystemProgram.programId,
         })
-        .rpc({ skipPreflight: true });
+        .rpc();
+      const market = await program.account.market.fetch(pdas.market);
+      expect(market.hasPriceHistory).to.be.true;
+    });
 
-      const duration = Date.now() - start;
-      console.log(`${duration}ms - Buy YES shares tx: ${tx}`);
+    it("rejects trades that skip the history", async () => {
+      try {
+        await buyIn(pdas, { yes: {} }, new BN(10_000_000));
+        expect.fail("Should have thrown PriceHistoryRequired");
+      } catch (err: any) {
+        expect(err.toString()).to.include("PriceHistoryRequired");
+      }
+    });
 
-      // Verify position
-      const position = await program.account.position.fetch(positionPDA);
-      expect(position.yesShares.toNumber()).to.be.greaterThan(0);
-      console.log("YES shares bought:", position.yesShares.toNumber());
+    it("folds every trade into the candle's OHLC and volume", async () => {
+      const open = await yesPrice();
+      await tradeWithHistory({ yes: {} }, new BN(20_000_000));
+      const afterYes = await yesPrice();
+      await tradeWithHistory({ no: {} }, new BN(30_000_000));
+      const close = await yesPrice();
+
+      const { candles, head } = await program.account.priceHistory.fetch(history);
+      expect(candles).to.have.lengthOf(1);
+      const candle = candles[head];
+      expect(candle.open.toNumber()).to.equal(open);
+      expect(candle.high.toNumber()).to.equal(Math.max(open, afterYes, close));
+      expect(candle.low.toNumber()).to.equal(Math.min(open, afterYes, close));
+      expect(candle.close.toNumber()).to.equal(close);
+      expect(candle.volume.toNumber()).to.equal(50_000_000);
     });
+  });
 
-    it("buys NO shares", async () => {
-      const amountIn = new BN(100_000_000); // 0.1 SOL
-      const minSharesOut = new BN(1);
+  describe("Conditional Markets", () => {
+    it("creates a market conditional on the parent outcome", async () => {
+      const childMarketId = new Uint8Array(32);
+      childMarketId[0] = 2;
+      const [childMarketPDA] = PublicKey.findProgramAddressSync(
+        [Buffer.from("market"), Buffer.from(childMarketId)],
+        program.programId
+      );
 
-      const tx = await program.methods
-        .buyShares({ no: {} }, amountIn, minSharesOut)
+      await program.methods
+        .createMarket(
+          Array.from(childMarketId),
+          strikePrice,
+          expiration.add(new BN(3600)),
+          maxConfidence,
+          "If SOL is above $100, will it stay above $100 an hour later?"
+        )
         .accounts({
-          market: marketPDA,
-          pool: poolPDA,
-          vault: vaultPDA,
-          position: positionPDA,
-          user: authority.publicKey,
+          market: childMarketPDA,
+          pythPriceAccount: mockPythPriceAccount.publicKey,
+          oracleFeed: oracleFeedPDA,
+          config: configPDA,
+          creatorEntry: null,
+          treasury: treasuryPDA,
+          authority: authority.publicKey,
           systemProgram: SystemProgram.programId,
         })
         .rpc({ skipPreflight: true });
 
-      console.log("Buy NO shares tx:", tx);
+      const tx = await program.methods
+        .setParentMarket({ yes: {} })
+        .accounts({
+          market: childMarketPDA,
+          parentMarket: marketPDA,
+          authority: authority.publicKey,
+        })
+        .rpc({ skipPreflight: true });
 
-      const position = await program.account.position.fetch(positionPDA);
-      expect(position.noShares.toNumber()).to.be.greaterThan(0);
-      console.log("NO shares bought:", position.noShares.toNumber());
+      console.log("Set Parent Market tx:", tx);
+
+      const child = await program.account.market.fetch(childMarketPDA);
+      expect(child.parentMarket.toBase58()).to.equal(marketPDA.toBase58());
+      expect(child.parentOutcome).to.deep.equal({ yes: {} });
+      const parent = await program.account.market.fetch(marketPDA);
+      expect(parent.unsettledChildren).to.equal(1);
+
+      // Settling the child releases the parent for closing
+      await program.methods
+        .cancelMarket()
+        .accounts({
+          market: childMarketPDA,
+          parentMarket: marketPDA,
+          batchAuction: null,
+          authority: authority.publicKey,
+        })
+        .rpc();
+      const released = await program.account.market.fetch(marketPDA);
+      expect(released.unsettledChildren).to.equal(0);
     });
+  });
 
-    it("sells YES shares", async () => {
-      const position = await program.account.position.fetch(positionPDA);
-      const sharesToSell = new BN(Math.floor(position.yesShares.toNumber() / 2)); // Sell half
-      const minAmountOut = new BN(1);
+  describe("Parlays", () => {
+    const parlayId = new BN(1);
+    let parlayPDA: PublicKey;
+    let legA: Awaited<ReturnType<typeof setupMarket>>;
+    let legB: Awaited<ReturnType<typeof setupMarket>>;
+
+    before(async () => {
+      legA = await setupMarket(10);
+      legB = await setupMarket(11);
+      await buyIn(legA, { yes: {} }, new BN(50_000_000));
+      await buyIn(legB, { no: {} }, new BN(50_000_000));
+      [parlayPDA] = PublicKey.findProgramAddressSync(
+        [Buffer.from("parlay"), authority.publicKey.toBuffer(), parlayId.toArrayLike(Buffer, "le", 8)],
+        program.programId
+      );
+    });
 
-      const start = Date.now();
-      const tx = await program.methods
-        .sellShares({ yes: {} }, sharesToSell, minAmountOut)
+    // Legs can only be resolved by hand once the manual grace period has passed
+    async function resolveLeg(ctx: Mocha.Context, leg: typeof legA, outcome: any) {
+      try {
+        await program.methods
+          .resolveMarketManual(outcome)
+          .accounts({ market: leg.market, parentMarket: null, batchAuction: null, resolver: authority.publicKey })
+          .rpc();
+      } catch (err: any) {
+        if (err.toString().includes("ManualResolutionTooEarly")) {
+          console.log("Skipping parlay settlement: legs cannot be resolved yet on this cluster");
+          ctx.skip();
+        }
+        throw err;
+      }
+    }
+
+    async function settleParlay(parlay: PublicKey, legs: (typeof legA)[]) {
+      return program.methods
+        .settleParlay()
         .accounts({
-          market: marketPDA,
-          pool: poolPDA,
-          vault: vaultPDA,
-          position: positionPDA,
-          user: authority.publicKey,
+          parlay,
+          owner: authority.publicKey,
+          config: configPDA,
+          treasury: authority.publicKey,
           systemProgram: SystemProgram.programId,
         })
-        .rpc({ skipPreflight: true });
+        .remainingAccounts(
+          legs.flatMap((leg) => [
+            { pubkey: leg.market, isSigner: false, isWritable: true },
+            { pubkey: leg.vault, isSigner: false, isWritable: true },
+            { pubkey: leg.position, isSigner: false, isWritable: true },
+          ])
+        )
+        .rpc({ commitment: "confirmed" });
+    }
+
+    it("locks shares from each leg's position", async () => {
+      const positionA = await program.account.position.fetch(legA.position);
+      const positionB = await program.account.position.fetch(legB.position);
+      const sharesA = new BN(Math.floor(positionA.yesShares.toNumber() / 2));
+      const sharesB = new BN(Math.floor(positionB.noShares.toNumber() / 2));
+
+      await program.methods
+        .createParlay(parlayId, [
+          { side: { yes: {} }, shares: sharesA },
+          { side: { no: {} }, shares: sharesB },
+        ])
+        .accounts({
+          config: configPDA,
+          parlay: parlayPDA,
+          owner: authority.publicKey,
+          systemProgram: SystemProgram.programId,
+        })
+        .remainingAccounts([
+          { pubkey: legA.market, isSigner: false, isWritable: false },
+          { pubkey: legA.position, isSigner: false, isWritable: true },
+          { pubkey: legB.market, isSigner: false, isWritable: false },
+          { pubkey: legB.position, isSigner: false, isWritable: true },
+        ])
+        .rpc();
+
+      const parlay = await program.account.parlay.fetch(parlayPDA);
+      expect(parlay.legs).to.have.lengthOf(2);
+      expect(parlay.legs[0].entryPrice.toNumber()).to.equal(positionA.yesAvgPrice.toNumber());
+
+      const positionAAfter = await program.account.position.fetch(legA.position);
+      expect(positionAAfter.yesShares.toNumber()).to.equal(
+        positionA.yesShares.toNumber() - sharesA.toNumber()
+      );
+    });
 
-      const duration = Date.now() - start;
-      console.log(`${duration}ms - Sell YES shares tx: ${tx}`);
+    it("rejects settlement while a leg is still active", async () => {
+      try {
+        await settleParlay(parlayPDA, [legA, legB]);
+        expect.fail("Should have thrown MarketNotResolved");
+      } catch (err: any) {
+        expect(err.toString()).to.include("MarketNotResolved");
+      }
+    });
 
-      const positionAfter = await program.account.position.fetch(positionPDA);
-      expect(positionAfter.yesShares.toNumber()).to.be.lessThan(position.yesShares.toNumber());
+    it("keeps a locked leg's position open until the parlay settles", async () => {
+      const position = await program.account.position.fetch(legA.position);
+      expect(position.openOrders).to.equal(1);
+      try {
+        await program.methods
+          .closePosition()
+          .accounts({ market: legA.market, position: legA.position, user: authority.publicKey })
+          .rpc();
+        expect.fail("Should have thrown OrdersOutstanding");
+      } catch (err: any) {
+        expect(err.toString()).to.include("OrdersOutstanding");
+      }
+    });
+
+    it("pays the combined payout when every leg wins", async function () {
+      await resolveLeg(this, legA, { yes: {} });
+      await resolveLeg(this, legB, { no: {} });
+
+      const parlay = await program.account.parlay.fetch(parlayPDA);
+      const expected = parlay.legs.reduce((sum, leg) => sum + leg.shares.toNumber(), 0);
+      const balanceBefore = await provider.connection.getBalance(authority.publicKey);
+
+      await settleParlay(parlayPDA, [legA, legB]);
+
+      // Winning shares plus the closed parlay's rent, less the transaction fee
+      const balanceAfter = await provider.connection.getBalance(authority.publicKey);
+      expect(balanceAfter - balanceBefore).to.be.greaterThan(expected - 10_000);
+      expect(await provider.connection.getAccountInfo(parlayPDA)).to.be.null;
+      const position = await program.account.position.fetch(legA.position);
+      expect(position.openOrders).to.equal(0);
+    });
+
+    it("forfeits the locked collateral to the treasury when a leg loses", async function () {
+      const losingId = new BN(2);
+      const [losingPDA] = PublicKey.findProgramAddressSync(
+        [Buffer.from("parlay"), authority.publicKey.toBuffer(), losingId.toArrayLike(Buffer, "le", 8)],
+        program.programId
+      );
+      const legC = await setupMarket(51);
+      const legD = await setupMarket(52);
+      await buyIn(legC, { yes: {} }, new BN(50_000_000));
+      await buyIn(legD, { yes: {} }, new BN(50_000_000));
+      const positionC = await program.account.position.fetch(legC.position);
+      const positionD = await program.account.position.fetch(legD.position);
+
+      await program.methods
+        .createParlay(losingId, [
+          { side: { yes: {} }, shares: positionC.yesShares },
+          { side: { yes: {} }, shares: positionD.yesShares },
+        ])
+        .accounts({
+          config: configPDA,
+          parlay: losingPDA,
+          owner: authority.publicKey,
+          systemProgram: SystemProgram.programId,
+        })
+        .remainingAccounts([
+          { pubkey: legC.market, isSigner: false, isWritable: false },
+          { pubkey: legC.position, isSigner: false, isWritable: true },
+          { pubkey: legD.market, isSigner: false, isWritable: false },
+          { pubkey: legD.position, isSigner: false, isWritable: true },
+        ])
+        .rpc();
+
+      await resolveLeg(this, legC, { yes: {} });
+      await resolveLeg(this, legD, { no: {} });
+
+      const vaultBefore = await provider.connection.getBalance(legC.vault);
+      const tx = await settleParlay(losingPDA, [legC, legD]);
+
+      // The winning leg's collateral leaves its vault for the treasury, not the owner
+      const vaultAfter = await provider.connection.getBalance(legC.vault);
+      expect(vaultBefore - vaultAfter).to.equal(positionC.yesShares.toNumber());
+
+      const txDetails = await provider.connection.getTransaction(tx, {
+        commitment: "confirmed",
+        maxSupportedTransactionVersion: 0,
+      });
+      const accountKeys = txDetails.transaction.message.staticAccountKeys;
+      const events = txDetails.meta.innerInstructions
+        .flatMap((inner) => inner.instructions)
+        .filter((ix) => accountKeys[ix.programIdIndex].equals(program.programId))
+        .map((ix) => {
+          const data = anchor.utils.bytes.bs58.decode(ix.data);
+          return program.coder.events.decode(
+            anchor.utils.bytes.base64.encode(Buffer.from(data.subarray(8)))
+          );
+        })
+        .filter((event) => event?.name === "parlaySettled");
+      expect(events).to.have.lengthOf(1);
+      expect(events[0].data.allWon).to.equal(false);
+      expect(events[0].data.payout.toNumber()).to.equal(0);
+      expect(await provider.connection.getAccountInfo(losingPDA)).to.be.null;
     });
   });
 
@@ -272,6 +2062,18 @@ describe("prediction_market", () => {
           payer: authority.publicKey,
           pda: marketPDA,
           market: marketPDA,
+          config: configPDA,
+        })
+        .remainingAccounts(remainingAccounts)
+        .rpc({ skipPreflight: true });
+
+      // The market records prices, so its history has to trade on the rollup too
+      await program.methods
+        .delegatePriceHistory()
+        .accounts({
+          payer: authority.publicKey,
+          priceHistory: priceHistoryPDA,
+          market: marketPDA,
         })
         .remainingAccounts(remainingAccounts)
         .rpc({ skipPreflight: true });
@@ -294,9 +2096,11 @@ describe("prediction_market", () => {
         .buyShares({ yes: {} }, new BN(50_000_000), new BN(1))
         .accounts({
           market: marketPDA,
+          config: configPDA,
           pool: poolPDA,
           vault: vaultPDA,
           position: positionPDA,
+          priceHistory: priceHistoryPDA,
           user: authority.publicKey,
           systemProgram: SystemProgram.programId,
         })
@@ -332,6 +2136,9 @@ describe("prediction_market", () => {
           payer: providerEphemeralRollup.wallet.publicKey,
           market: marketPDA,
           pool: poolPDA,
+          orderBook: null,
+          batchAuction: null,
+          priceHistory: priceHistoryPDA,
         })
         .transaction();
 
@@ -364,6 +2171,9 @@ describe("prediction_market", () => {
           payer: providerEphemeralRollup.wallet.publicKey,
           market: marketPDA,
           pool: poolPDA,
+          orderBook: null,
+          batchAuction: null,
+          priceHistory: priceHistoryPDA,
         })
         .transaction();
 
@@ -394,6 +2204,9 @@ describe("prediction_market", () => {
           .accounts({
             market: marketPDA,
             pythPriceAccount: mockPythPriceAccount.publicKey,
+            oracleFeed: oracleFeedPDA,
+            parentMarket: null,
+            batchAuction: null,
             resolver: authority.publicKey,
           })
           .rpc();
@@ -443,6 +2256,43 @@ describe("prediction_market", () => {
       console.log(`Implied NO Probability: ${noProb}%`);
     });
 
+    it("quotes a YES buy via simulation", async () => {
+      const amountIn = new BN(10_000_000);
+      const quote = await program.methods
+        .quoteBuy({ yes: {} }, amountIn)
+        .accounts({
+          market: marketPDA,
+          config: configPDA,
+          pool: poolPDA,
+        })
+        .view();
+
+      console.log("\n--- Buy Quote ---");
+      console.log("Shares Out:", quote.amountOut.toString());
+      console.log("Fee:", quote.fee.toString());
+      console.log("Price Impact (bps):", quote.priceImpactBps.toString());
+
+      expect(quote.amountIn.toNumber()).to.equal(amountIn.toNumber());
+      expect(quote.amountOut.toNumber()).to.be.greaterThan(0);
+      expect(quote.priceAfter.toNumber()).to.be.greaterThan(quote.priceBefore.toNumber());
+    });
+
+    it("rejects a quote for a buy too small to execute", async () => {
+      try {
+        await program.methods
+          .quoteBuy({ yes: {} }, new BN(100))
+          .accounts({
+            market: marketPDA,
+            config: configPDA,
+            pool: poolPDA,
+          })
+          .view();
+        expect.fail("Should have thrown OutputTooSmall");
+      } catch (err: any) {
+        expect(err.toString()).to.include("OutputTooSmall");
+      }
+    });
+
     it("queries position state with P/L", async () => {
       const position = await program.account.position.fetch(positionPDA);
       const pool = await program.account.pool.fetch(poolPDA);
@@ -453,7 +2303,11 @@ describe("prediction_market", () => {
       console.log("NO Shares:", position.noShares.toString());
       console.log("YES Avg Price:", position.yesAvgPrice.toString());
       console.log("NO Avg Price:", position.noAvgPrice.toString());
-      console.log("Claimed:", position.claimed);
+      console.log("Claimed shares:", position.claimedShares.toString());
+      console.log("YES Cost / Proceeds:", position.yesCost.toString(), position.yesProceeds.toString());
+      console.log("NO Cost / Proceeds:", position.noCost.toString(), position.noProceeds.toString());
+      console.log("Realized P/L:", position.realizedPnl.toString());
+      console.log("Fees paid:", position.feesPaid.toString());
 
       // Calculate current prices
       const totalReserve = pool.yesReserve.toNumber() + pool.noReserve.toNumber();